@@ -3,9 +3,9 @@ use crate::widgets::UserInterface;
 use crate::{Backend, Frontend, GlobalState};
 
 use anyhow::{anyhow, Context, Result};
-use slog::{info, debug};
+use slog::{info, debug, error};
 use spin_sleep;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use iced_winit::{
     conversion,
@@ -17,10 +17,13 @@ use iced_winit::{
 
 use lucien_core as core;
 use lucien_core::logger::logger;
-use lucien_core::resources::Project;
+use lucien_core::resources::{assets, Project};
+use lucien_core::scheduler::Scheduler;
+use lucien_core::time::{Clock, SystemClock};
+use crate::input::input_event;
+use crate::vm::input::{end_input_frame, handle_input};
 use crate::vm::Scripting;
-
-static VERSION: &str = env!("CARGO_PKG_VERSION");
+use crate::watcher::watch_project;
 
 // Store global state singleton so we can access it
 use lazy_static::lazy_static;
@@ -69,30 +72,40 @@ impl Application {
         // create event loop
         let event_loop = EventLoop::<Message>::with_user_event();
 
-        // create ui layout
-        let ui = UserInterface::new();
-        // create winit window
-        let g_state = GlobalState::new(&event_loop);
-        let mut backend = Backend::new(&g_state).context("Failed to create backend")?;
+        // create winit window, as the manifest describes
+        let manifest = self.project()?.manifest().clone();
+        let g_state = GlobalState::new(&event_loop, &manifest.window);
+        let mut backend =
+            Backend::new(&g_state, self.project()?).context("Failed to create backend")?;
+        // create ui layout, starts with scene clear color
+        let ui = UserInterface::new().with_background_color(backend.clear_color());
         let mut frontend = Frontend::new(&g_state, ui).context("Failed to create frontend")?;
         info!(logger(), "window created successfully.");
-
-        g_state.window
-            .set_title(format!("lucien v{}", VERSION).as_str());
+        // scripts change the scene that backend renders
+        self.vm.bind_scene(backend.renderer.scene());
 
         // set glob singleton
         *GLOB.lock().unwrap() = Some(g_state);
 
         // wake up main loop on tick and dispatch a custom event
-        // from a different thread.
+        // from a different thread; the thread sleeps until the next
+        // frame is due, on the same clock as the main loop
         let proxy = event_loop.create_proxy();
+        let clock = SystemClock::new();
+        let mut scheduler = Scheduler::new(manifest.loop_config());
+        let mut pacer = scheduler.clone();
         std::thread::spawn(move || {
             loop {
-                // todo use actual frame rate
-                spin_sleep::sleep(std::time::Duration::from_millis(10));
+                spin_sleep::sleep(pacer.wait_time(clock.now()));
+                pacer.frame(clock.now());
                 proxy.send_event(Message::Tick).ok();
             }
         });
+        // reload scripts and shaders when they are saved
+        let root = self.project()?.path("").context("Failed to find project root")?;
+        if let Err(e) = watch_project(root, event_loop.create_proxy()) {
+            error!(logger(), "hot reload is disabled: {:?}", e);
+        }
         // cache engine customized events
         // thank goodness we don't have event loops on
         // different threads so we don't need to use an
@@ -107,9 +120,12 @@ impl Application {
         self.vm
             .call(self.vm.start_fn())
             .context("Failed to call start function")?;
-        self.vm
-            .call(self.vm.update_fn())
-            .context("Failed to call update function")?;
+        // scripts update the scene on every tick, the vm is shared
+        // with the main loop
+        let mut vm = self.vm.clone();
+        let mut update_fn = vm.update_fn();
+        let mut fixed_update_fn = vm.fixed_update_fn();
+        let step = scheduler.config().update_step().as_secs_f64();
 
         event_loop.run(move |event, _, control_flow| {
             // unwrap global singleton so we can update it
@@ -120,7 +136,6 @@ impl Application {
             // WaitUntil can be useful but I didn't know it was there before
             *control_flow = ControlFlow::Wait;
 
-            // todo synchronize frame rate, drop frame if something takes too long
             match event {
                 // *handle* user customized events,
                 // cache them in a vector so we *consume* them later;
@@ -134,17 +149,71 @@ impl Application {
                     // consume user events
                     if !messages.is_empty() {
                         for msg in messages.drain(..) {
+                            // a changed file is read again when it's reloaded
+                            if let Message::LoadProjectChange(path) = &msg {
+                                assets().invalidate(Path::new(path));
+                            }
                             match msg {
                                 // here is where scene update should happen
                                 // yes, you only ask the window to redraw on each tick
                                 Message::Tick => {
+                                    // ticks that pile up while a frame takes too
+                                    // long are early for the next frame, skip them
+                                    let frame = scheduler.frame(clock.now());
+                                    if !frame.render {
+                                        continue;
+                                    }
+                                    if frame.dropped > 0 {
+                                        debug!(logger(), "dropped {} frames", frame.dropped);
+                                    }
+                                    vm.schedule(&frame);
                                     // todo spawn a thread, abort it on timeout
                                     // and request render on finish; also pass in logger
                                     // reuse a tokio runtime to spawn async tasks;
                                     // update should be in a separate thread than render thread
+                                    // a script error shouldn't stop the engine
+                                    for _ in 0..frame.updates {
+                                        let fixed_update_fn = fixed_update_fn.clone();
+                                        if let Err(e) = vm.fixed_update(fixed_update_fn, step) {
+                                            error!(logger(), "{:#}", e);
+                                        }
+                                    }
+                                    if let Err(e) = vm.update(update_fn.clone(), &clock) {
+                                        error!(logger(), "{:#}", e);
+                                    }
+                                    // scripts have seen input of this frame
+                                    end_input_frame();
                                     backend.update(&glob).expect("3D update");
                                     glob.window.request_redraw();
                                 }
+                                // a saved script replaces the running one, unless
+                                // it fails to load, then the running one goes on
+                                Message::LoadProjectChange(path) if path.ends_with(".wren") => {
+                                    match vm.reload() {
+                                        Ok(new_vm) => {
+                                            vm = new_vm;
+                                            update_fn = vm.update_fn();
+                                            fixed_update_fn = vm.fixed_update_fn();
+                                            info!(logger(), "script reloaded: {}", path);
+                                        }
+                                        Err(e) => {
+                                            error!(logger(), "{:?}", e);
+                                        }
+                                    }
+                                }
+                                // a broken shader is reported, the old pipelines
+                                // keep rendering until it's fixed
+                                Message::LoadProjectChange(path) if path.ends_with(".glsl") => {
+                                    match backend.reload_shaders(&glob) {
+                                        Ok(_) => {
+                                            info!(logger(), "shaders reloaded: {}", path);
+                                            glob.window.request_redraw();
+                                        }
+                                        Err(e) => {
+                                            error!(logger(), "{:?}", e);
+                                        }
+                                    }
+                                }
                                 // todo other user events
                                 _ => {}
                             }
@@ -182,6 +251,9 @@ impl Application {
                     // handle window events, changes states in glob so UI + backend
                     // could access the changes. No actual changes are made, until
                     // they are consumed above.
+                    if let Some(input) = input_event(&event) {
+                        handle_input(input);
+                    }
                     match event {
                         WindowEvent::CursorMoved { position, .. } => {
                             frontend.cursor_position = position;
@@ -1,6 +1,7 @@
 use crate::{Frontend, GlobalState};
 use anyhow::{Context, Result};
 use iced_wgpu::wgpu;
+use lucien_core::resources::Project;
 use lucien_render as render;
 
 pub(crate) struct Backend {
@@ -9,14 +10,26 @@ pub(crate) struct Backend {
 }
 
 impl Backend {
-    pub fn new(glob: &GlobalState) -> Result<Self> {
-        let settings = render::RenderSettings::new(glob.get_size());
-        let renderer = render::Renderer::new(&glob.device, &glob.queue, &settings)
+    pub fn new(glob: &GlobalState, project: &Project) -> Result<Self> {
+        let mut settings = render::RenderSettings::new(glob.get_size());
+        settings.shader_dir = project.manifest().shader_dir.clone();
+        let desc = project.load_scene()?;
+        let renderer = render::Renderer::new(&glob.device, &glob.queue, &settings, &desc)
             .context("Failed to create 3D renderer")?;
 
         Ok(Self { settings, renderer })
     }
 
+    // clear color of the scene, UI can change it later
+    pub fn clear_color(&self) -> wgpu::Color {
+        self.renderer.scene().lock().unwrap().clear_color
+    }
+
+    // rebuild pipelines after shaders are saved
+    pub fn reload_shaders(&mut self, glob: &GlobalState) -> Result<()> {
+        self.renderer.reload_shaders(&glob.device)
+    }
+
     pub fn update(&mut self, glob: &GlobalState) -> Result<()> {
         self.renderer.update(&glob.device, &glob.queue);
         Ok(())
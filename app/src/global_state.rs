@@ -5,7 +5,10 @@ use anyhow::Result;
 use futures::executor::block_on;
 use iced_wgpu::{wgpu, Viewport};
 use iced_winit::{futures, winit, Size};
+use lucien_core::resources::WindowDesc;
+use winit::dpi::LogicalSize;
 use winit::event_loop::EventLoop;
+use winit::window::WindowBuilder;
 
 #[derive(Debug)]
 pub(crate) struct GlobalState {
@@ -19,8 +22,12 @@ pub(crate) struct GlobalState {
 }
 
 impl GlobalState {
-    pub fn new(event_loop: &EventLoop<Message>) -> Self {
-        let window = winit::window::Window::new(event_loop).unwrap();
+    pub fn new(event_loop: &EventLoop<Message>, desc: &WindowDesc) -> Self {
+        let window = WindowBuilder::new()
+            .with_title(desc.title.as_str())
+            .with_inner_size(LogicalSize::new(desc.width, desc.height))
+            .build(event_loop)
+            .unwrap();
         let size = window.inner_size();
         let viewport =
             Viewport::with_physical_size(Size::new(size.width, size.height), window.scale_factor());
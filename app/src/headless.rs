@@ -0,0 +1,104 @@
+use crate::init_headless;
+use crate::vm::Scripting;
+
+use anyhow::{Context, Result};
+use iced_winit::futures::executor::block_on;
+use slog::info;
+use std::path::PathBuf;
+
+use lucien_core as core;
+use lucien_core::logger::logger;
+use lucien_core::resources::Project;
+use lucien_core::scheduler::Scheduler;
+use lucien_core::time::{Clock, ManualClock};
+use lucien_render::{RenderSettings, Renderer};
+
+// Render a project offscreen without a window, each frame is
+// saved as png, e.g. to produce golden images on CI.
+pub struct Headless {
+    project: Project,
+    vm: Scripting,
+    frames: u32,
+    out_dir: PathBuf,
+}
+
+impl Headless {
+    // accepts the args of `render` sub command
+    pub fn new(args: &core::ArgFlags) -> Result<Self> {
+        let root = args.value_of("project").unwrap();
+        let mut proj = Project::new().base_dir(root);
+        proj.load().context("Failed to load project")?;
+        let vm = Scripting::new(&proj).context("Failed to start vm")?;
+        let frames = args
+            .value_of("frames")
+            .unwrap()
+            .parse::<u32>()
+            .context("Failed to parse number of frames")?;
+        let out_dir = PathBuf::from(args.value_of("out").unwrap());
+
+        Ok(Self {
+            project: proj,
+            vm,
+            frames,
+            out_dir,
+        })
+    }
+
+    pub fn run(&mut self) -> Result<()> {
+        let (device, queue) = block_on(init_headless()).context("Failed to init gpu")?;
+        let mut settings = RenderSettings::default();
+        settings.shader_dir = self.project.manifest().shader_dir.clone();
+        let desc = self.project.load_scene()?;
+        let mut renderer = Renderer::new(&device, &queue, &settings, &desc)
+            .context("Failed to create 3D renderer")?;
+        std::fs::create_dir_all(&self.out_dir)
+            .with_context(|| format!("Failed to create directory {:?}", &self.out_dir))?;
+
+        self.vm.bind_scene(renderer.scene());
+        self.vm.init();
+        self.vm
+            .call(self.vm.start_fn())
+            .context("Failed to call start function")?;
+
+        // frames are rendered as if the game runs at target frame rate,
+        // so the output doesn't depend on how fast we render
+        let mut scheduler = Scheduler::new(self.project.manifest().loop_config());
+        let frame_time = scheduler.config().frame_time();
+        let step = scheduler.config().update_step().as_secs_f64();
+        let clock = ManualClock::new();
+        let update_fn = self.vm.update_fn();
+        let fixed_update_fn = self.vm.fixed_update_fn();
+
+        info!(logger(), "rendering {} frames offscreen.", self.frames);
+        for frame in 0..self.frames {
+            if frame > 0 {
+                clock.advance(frame_time);
+            }
+            let due = scheduler.frame(clock.now());
+            self.vm.schedule(&due);
+            for _ in 0..due.updates {
+                self.vm
+                    .fixed_update(fixed_update_fn.clone(), step)
+                    .context("Failed to call fixed_update function")?;
+            }
+            self.vm
+                .update(update_fn.clone(), &clock)
+                .context("Failed to call update function")?;
+            renderer.update(&device, &queue);
+            renderer
+                .render(&settings, &device, &queue)
+                .context("3D render failed")?;
+            renderer
+                .read_to_buffer(&device, &queue)
+                .context("Failed to read render result")?;
+            let rgba = block_on(renderer.as_rgba(&device)).context("Failed to convert frame")?;
+
+            let path = self.out_dir.join(format!("frame_{:04}.png", frame));
+            rgba.save(&path)
+                .with_context(|| format!("Failed to save frame {:?}", &path))?;
+            info!(logger(), "frame saved to {:?}", path);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,55 @@
+use iced_winit::winit::event::{
+    ElementState, KeyboardInput, MouseButton, MouseScrollDelta, WindowEvent,
+};
+use lucien_core::input::InputEvent;
+
+// scroll in pixels, e.g. from touchpads, is converted to lines
+const PIXELS_PER_LINE: f32 = 20.0;
+
+// convert a window event to an input event for scripts
+pub(crate) fn input_event(event: &WindowEvent) -> Option<InputEvent> {
+    match event {
+        WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } => {
+            // `VirtualKeyCode::Space` is "space"
+            let name = format!("{:?}", key).to_lowercase();
+            Some(button_event(*state, name))
+        }
+        WindowEvent::MouseInput { state, button, .. } => {
+            let name = match button {
+                MouseButton::Left => "mouse_left".to_string(),
+                MouseButton::Right => "mouse_right".to_string(),
+                MouseButton::Middle => "mouse_middle".to_string(),
+                MouseButton::Other(n) => format!("mouse_{}", n),
+            };
+            Some(button_event(*state, name))
+        }
+        WindowEvent::CursorMoved { position, .. } => Some(InputEvent::CursorMoved(
+            position.x as f32,
+            position.y as f32,
+        )),
+        WindowEvent::MouseWheel { delta, .. } => match delta {
+            MouseScrollDelta::LineDelta(x, y) => Some(InputEvent::Scroll(*x, *y)),
+            MouseScrollDelta::PixelDelta(position) => Some(InputEvent::Scroll(
+                position.x as f32 / PIXELS_PER_LINE,
+                position.y as f32 / PIXELS_PER_LINE,
+            )),
+        },
+        WindowEvent::Focused(false) => Some(InputEvent::FocusLost),
+        _ => None,
+    }
+}
+
+fn button_event(state: ElementState, name: String) -> InputEvent {
+    match state {
+        ElementState::Pressed => InputEvent::Pressed(name),
+        ElementState::Released => InputEvent::Released(name),
+    }
+}
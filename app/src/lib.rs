@@ -5,7 +5,9 @@ use iced_winit::winit;
 mod backend;
 mod frontend;
 mod global_state;
+mod input;
 mod vm;
+mod watcher;
 
 use backend::*;
 use frontend::*;
@@ -14,10 +16,15 @@ use global_state::*;
 mod application;
 pub use application::*;
 
+mod headless;
+pub use headless::*;
+
+mod pack;
+pub use pack::*;
+
 pub mod message;
 pub mod widgets;
 
-#[allow(dead_code)]
 async fn init_headless() -> Result<(wgpu::Device, wgpu::Queue)> {
     let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
     let adapter = instance
@@ -0,0 +1,39 @@
+use anyhow::{Context, Result};
+use slog::info;
+use std::path::PathBuf;
+
+use lucien_core as core;
+use lucien_core::logger::logger;
+use lucien_core::resources::{write_pack, PACK_EXTENSION};
+
+// Pack a project into one file, it's run like the project
+// directory, e.g. `lucien game.lpak`.
+// Accepts the args of `pack` sub command.
+pub fn pack(args: &core::ArgFlags) -> Result<()> {
+    let project = args.value_of("project").unwrap();
+    let root = PathBuf::from(project)
+        .canonicalize()
+        .with_context(|| format!("Project {} not found", project))?;
+    let out = match args.value_of("out") {
+        Some(out) => PathBuf::from(out),
+        None => {
+            let name = root
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or("lucien");
+            PathBuf::from(format!("{}.{}", name, PACK_EXTENSION))
+        }
+    };
+    let stats = write_pack(&root, &out, args.is_present("hash"))
+        .with_context(|| format!("Failed to pack project {:?}", root))?;
+    info!(
+        logger(),
+        "packed {} files into {:?}, {} bytes compressed to {}",
+        stats.files,
+        out,
+        stats.size,
+        stats.packed_size
+    );
+
+    Ok(())
+}
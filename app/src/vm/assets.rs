@@ -0,0 +1,66 @@
+use crate::vm::error::{abort, abort_init};
+use lucien_core::resources::{
+    assets, LoadState, MeshData, ScriptSource, ShaderSource, TextureData,
+};
+use ruwren::{Class, VM};
+use std::path::Path;
+
+pub struct WrenAssets;
+
+impl Class for WrenAssets {
+    fn initialize(vm: &VM) -> Self {
+        abort_init(vm, "Assets", WrenAssets)
+    }
+}
+
+// static methods for wren, paths are relative to project root
+impl WrenAssets {
+    // load a file in background, the kind of asset is its extension
+    pub fn load(vm: &VM) {
+        let path = get_slot!(vm => string 1);
+        let ext = Path::new(&path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_lowercase());
+        match ext.as_deref() {
+            Some("obj") | Some("gltf") | Some("glb") => assets().load_async::<MeshData>(&path),
+            Some("png") | Some("jpg") | Some("jpeg") | Some("bmp") | Some("tga") => {
+                assets().load_async::<TextureData>(&path)
+            }
+            Some("glsl") => assets().load_async::<ShaderSource>(&path),
+            Some("wren") => assets().load_async::<ScriptSource>(&path),
+            _ => abort(vm, format!("unknown kind of asset {}", path)),
+        }
+    }
+
+    // "pending", "loaded", "failed", or "unloaded"
+    pub fn state(vm: &VM) {
+        let path = get_slot!(vm => string 1);
+        let state = match assets().state_all(&path) {
+            Some(LoadState::Pending) => "pending",
+            Some(LoadState::Loaded) => "loaded",
+            Some(LoadState::Failed(_)) => "failed",
+            None => "unloaded",
+        };
+        vm.set_slot_string(0, state);
+    }
+
+    // error of a failed load, or null
+    pub fn error(vm: &VM) {
+        let path = get_slot!(vm => string 1);
+        match assets().state_all(&path) {
+            Some(LoadState::Failed(e)) => vm.set_slot_string(0, e),
+            _ => vm.set_slot_null(0),
+        }
+    }
+
+    pub fn pending(vm: &VM) {
+        vm.set_slot_double(0, assets().pending() as f64);
+    }
+
+    // let the engine unload a file that's not used
+    pub fn release(vm: &VM) {
+        let path = get_slot!(vm => string 1);
+        assets().release_all(&path);
+    }
+}
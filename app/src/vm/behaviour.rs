@@ -0,0 +1,186 @@
+use crate::vm::graphics::with_scene;
+use anyhow::{anyhow, Result};
+use lucien_core::ecs::Entity;
+use lucien_core::logger::logger;
+use ruwren::{FunctionHandle, FunctionSignature, Handle, VMWrapper};
+use slog::error;
+use std::fmt;
+use std::rc::Rc;
+
+// A behaviour attached to an entity, an instance of a wren class
+struct Instance {
+    entity: Entity,
+    object: Rc<Handle>,
+    started: bool,
+    // an instance that fails is removed, others keep running
+    failed: bool,
+}
+
+// Behaviours of entities, attached in scripts with `Behaviours.attach`.
+// Wren holds attached instances until they're taken on next update;
+// an instance is started before its first update, and destroyed when
+// its entity is despawned or scripts are reloaded.
+pub struct Behaviours {
+    instances: Vec<Instance>,
+    // `Behaviours` class of behaviour module
+    class: Rc<Handle>,
+    pending_count: Rc<FunctionHandle>,
+    pending_entity: Rc<FunctionHandle>,
+    pending: Rc<FunctionHandle>,
+    clear_pending: Rc<FunctionHandle>,
+    // callbacks of instances
+    start: Rc<FunctionHandle>,
+    update: Rc<FunctionHandle>,
+    destroy: Rc<FunctionHandle>,
+}
+
+impl Behaviours {
+    // behaviour module must be interpreted in the vm
+    pub fn new(vm: &VMWrapper) -> Self {
+        vm.execute(|vm| {
+            vm.ensure_slots(1);
+            vm.get_variable("behaviour", "Behaviours", 0);
+        });
+        let class = vm.get_slot_handle(0);
+        let handle =
+            |name: &str, arity| vm.make_call_handle(FunctionSignature::new_function(name, arity));
+
+        Self {
+            instances: vec![],
+            class,
+            pending_count: handle("pending_count", 0),
+            pending_entity: handle("pending_entity", 1),
+            pending: handle("pending", 1),
+            clear_pending: handle("clear_pending", 0),
+            start: handle("start", 0),
+            update: handle("update", 1),
+            destroy: handle("destroy", 0),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+
+    // take new behaviours, destroy those of despawned entities,
+    // then start new ones and update all of them
+    pub fn update(&mut self, vm: &VMWrapper, dt: f64) {
+        if let Err(e) = self.take_pending(vm) {
+            error!(logger(), "* [wren] failed to attach behaviours: {}", e);
+        }
+
+        // scene is unlocked before calling scripts, they change it
+        let despawned: Vec<bool> = with_scene(|scene| {
+            self.instances
+                .iter()
+                .map(|instance| !scene.world.is_alive(instance.entity))
+                .collect()
+        })
+        .unwrap_or_else(|| vec![false; self.instances.len()]);
+        let mut despawned = despawned.into_iter();
+        let (gone, instances): (Vec<Instance>, Vec<Instance>) = self
+            .instances
+            .drain(..)
+            .partition(|_| despawned.next().unwrap_or(false));
+        self.instances = instances;
+        for instance in gone.iter() {
+            self.call(vm, instance, &self.destroy, &[]);
+        }
+
+        let (start, update) = (self.start.clone(), self.update.clone());
+        for i in 0..self.instances.len() {
+            if !self.instances[i].started {
+                self.instances[i].started = true;
+                self.run(vm, i, &start, &[]);
+            }
+            self.run(vm, i, &update, &[dt]);
+        }
+        self.instances.retain(|instance| !instance.failed);
+    }
+
+    // destroy every behaviour, e.g. once scripts are reloaded;
+    // ones not taken yet are dropped without being started
+    pub fn destroy(&mut self, vm: &VMWrapper) {
+        if let Err(e) = self.call_class(vm, &self.clear_pending, None) {
+            error!(logger(), "* [wren] {}", e);
+        }
+        let instances = std::mem::take(&mut self.instances);
+        for instance in instances.iter() {
+            self.call(vm, instance, &self.destroy, &[]);
+        }
+    }
+
+    fn take_pending(&mut self, vm: &VMWrapper) -> Result<()> {
+        self.call_class(vm, &self.pending_count, None)?;
+        let count = vm.execute(|vm| vm.get_slot_double(0)).unwrap_or(0.0) as usize;
+        for i in 0..count {
+            self.call_class(vm, &self.pending_entity, Some(i))?;
+            let id = vm
+                .execute(|vm| vm.get_slot_double(0))
+                .ok_or_else(|| anyhow!("entity of behaviour {} is not an entity", i))?;
+            self.call_class(vm, &self.pending, Some(i))?;
+            self.instances.push(Instance {
+                entity: Entity::from_bits(id as u64),
+                object: vm.get_slot_handle(0),
+                started: false,
+                failed: false,
+            });
+        }
+        self.call_class(vm, &self.clear_pending, None)
+    }
+
+    // call a static method of `Behaviours`, with an index if it takes one;
+    // the result is left in slot 0
+    fn call_class(
+        &self, vm: &VMWrapper, method: &Rc<FunctionHandle>, index: Option<usize>,
+    ) -> Result<()> {
+        vm.execute(|vm| vm.ensure_slots(2));
+        vm.set_slot_handle(0, &self.class);
+        if let Some(index) = index {
+            vm.execute(|vm| vm.set_slot_double(1, index as f64));
+        }
+        vm.call_handle(method).map_err(|e| anyhow!("{}", e))
+    }
+
+    // call a method of the i-th instance, it fails alone
+    fn run(&mut self, vm: &VMWrapper, i: usize, method: &Rc<FunctionHandle>, args: &[f64]) {
+        if !self.instances[i].failed && !self.call(vm, &self.instances[i], method, args) {
+            self.instances[i].failed = true;
+        }
+    }
+
+    // returns false if the method fails, the error is logged
+    fn call(
+        &self, vm: &VMWrapper, instance: &Instance, method: &Rc<FunctionHandle>, args: &[f64],
+    ) -> bool {
+        vm.execute(|vm| vm.ensure_slots(args.len() + 1));
+        vm.set_slot_handle(0, &instance.object);
+        vm.execute(|vm| {
+            for (i, arg) in args.iter().enumerate() {
+                vm.set_slot_double(i + 1, *arg);
+            }
+        });
+        match vm.call_handle(method) {
+            Ok(_) => true,
+            Err(e) => {
+                error!(
+                    logger(),
+                    "* [wren] behaviour of {:?} stopped: {}", instance.entity, e
+                );
+                false
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Behaviours {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Behaviours")
+            .field("instances", &self.instances.len())
+            .finish()
+    }
+}
@@ -0,0 +1,54 @@
+use ruwren::VM;
+
+// Abort the current fiber with a runtime error. The script that called
+// the foreign method stops with the message and a stack trace, the
+// engine keeps running.
+pub fn abort(vm: &VM, message: String) {
+    vm.set_slot_string(0, message);
+    vm.abort_fiber(0);
+}
+
+// Abort when wren tries to construct a foreign class that only
+// the engine creates, e.g. `Vec3.new()`; the returned object
+// is never used, the fiber is aborted.
+pub fn abort_init<T>(vm: &VM, class: &str, placeholder: T) -> T {
+    abort(vm, format!("{} can't be created in wren", class));
+    placeholder
+}
+
+// Read an argument of a foreign method, like `get_slot_checked!`,
+// but an argument of the wrong type aborts the fiber and returns
+// from the foreign method instead of panicking.
+macro_rules! get_slot {
+    ($vm:expr => num $slot:expr) => {
+        match $vm.get_slot_double($slot) {
+            Some(value) => value,
+            None => return $crate::vm::error::abort_argument($vm, $slot, "a number"),
+        }
+    };
+    ($vm:expr => bool $slot:expr) => {
+        match $vm.get_slot_bool($slot) {
+            Some(value) => value,
+            None => return $crate::vm::error::abort_argument($vm, $slot, "a bool"),
+        }
+    };
+    ($vm:expr => string $slot:expr) => {
+        match $vm.get_slot_string($slot) {
+            Some(value) => value,
+            None => return $crate::vm::error::abort_argument($vm, $slot, "a string"),
+        }
+    };
+    ($vm:expr => foreign $class:ty => $slot:expr) => {
+        match $vm.get_slot_foreign::<$class>($slot) {
+            Some(value) => value,
+            None => {
+                let class = stringify!($class).trim_start_matches("Wren");
+                return $crate::vm::error::abort_argument($vm, $slot, class);
+            }
+        }
+    };
+}
+
+pub fn abort_argument(vm: &VM, slot: usize, expected: &str) {
+    abort(vm, format!("expected {} as argument {}", expected, slot));
+}
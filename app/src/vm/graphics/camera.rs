@@ -0,0 +1,60 @@
+use crate::vm::error::abort_init;
+use crate::vm::graphics::{with_scene, WrenVec3};
+use ruwren::{send_foreign, Class, VM};
+
+// Handle to the camera of the live scene, get it with `Graphics.camera`
+#[derive(Debug, Clone, Copy)]
+pub struct WrenCamera;
+
+impl Class for WrenCamera {
+    fn initialize(vm: &VM) -> Self {
+        abort_init(vm, "Camera", WrenCamera)
+    }
+}
+
+// getters return null if scene is not ready
+impl WrenCamera {
+    pub fn eye(&self, vm: &VM) {
+        match with_scene(|scene| scene.camera.eye) {
+            Some(eye) => {
+                send_foreign!(vm, "graphics", "Vec3", WrenVec3(eye) => 0);
+            }
+            None => vm.set_slot_null(0),
+        }
+    }
+
+    pub fn look_at(&self, vm: &VM) {
+        match with_scene(|scene| scene.camera.look_at) {
+            Some(look_at) => {
+                send_foreign!(vm, "graphics", "Vec3", WrenVec3(look_at) => 0);
+            }
+            None => vm.set_slot_null(0),
+        }
+    }
+
+    // vertical field of view in radians
+    pub fn fov(&self, vm: &VM) {
+        match with_scene(|scene| scene.camera.fov) {
+            Some(fov) => vm.set_slot_double(0, fov as f64),
+            None => vm.set_slot_null(0),
+        }
+    }
+
+    // accepts a vec3
+    pub fn set_eye(&self, vm: &VM) {
+        let eye = get_slot!(vm => foreign WrenVec3 => 1).0;
+        with_scene(|scene| scene.camera.eye = eye);
+    }
+
+    // accepts a vec3
+    pub fn set_look_at(&self, vm: &VM) {
+        let look_at = get_slot!(vm => foreign WrenVec3 => 1).0;
+        with_scene(|scene| scene.camera.look_at = look_at);
+    }
+
+    // accepts a number in radians
+    pub fn set_fov(&self, vm: &VM) {
+        let fov = get_slot!(vm => num 1);
+        with_scene(|scene| scene.camera.fov = fov as f32);
+    }
+}
@@ -0,0 +1,156 @@
+use crate::vm::error::abort_init;
+use crate::vm::graphics::{with_scene, Light, WrenVec3};
+use lucien_core::ecs::Entity;
+use lucien_render::{Camera, MeshRenderer, Name, Scene, SceneNode, Transform};
+use ruwren::{send_foreign, Class, VM};
+
+// Handle to an entity of the live scene. Spawn one with `Graphics.spawn`,
+// or find one with `Graphics.entity(name)`; every model in scene file
+// is drawn by an entity named after the model.
+#[derive(Debug, Clone, Copy)]
+pub struct WrenEntity(pub Entity);
+
+impl Class for WrenEntity {
+    fn initialize(vm: &VM) -> Self {
+        abort_init(vm, "Entity", WrenEntity(Entity::from_bits(u64::MAX)))
+    }
+}
+
+// methods on a despawned entity do nothing, and return false or null
+impl WrenEntity {
+    // a number that identifies the entity
+    pub fn id(&self, vm: &VM) {
+        vm.set_slot_double(0, self.0.to_bits() as f64);
+    }
+
+    pub fn alive(&self, vm: &VM) {
+        let alive = with_scene(|scene| scene.world.is_alive(self.0)).unwrap_or(false);
+        vm.set_slot_bool(0, alive);
+    }
+
+    // position relative to the node it's attached to
+    pub fn position(&self, vm: &VM) {
+        let position = with_scene(|scene| {
+            scene
+                .world
+                .get::<Transform>(self.0)
+                .map(|transform| transform.translation)
+        });
+        match position.flatten() {
+            Some(position) => {
+                send_foreign!(vm, "graphics", "Vec3", WrenVec3(position) => 0);
+            }
+            None => vm.set_slot_null(0),
+        }
+    }
+
+    // remove the entity and its components,
+    // returns false if it's despawned already
+    pub fn despawn(&self, vm: &VM) {
+        let despawned = with_scene(|scene| scene.world.despawn(self.0)).unwrap_or(false);
+        vm.set_slot_bool(0, despawned);
+    }
+
+    // accepts a vec3
+    pub fn set_position(&self, vm: &VM) {
+        let position = get_slot!(vm => foreign WrenVec3 => 1).0;
+        self.transform(|transform| transform.translation = position);
+    }
+
+    // accepts euler angles in degrees, applied in y, x, z order as in scene file
+    pub fn set_rotation(&self, vm: &VM) {
+        let x = get_slot!(vm => num 1) as f32;
+        let y = get_slot!(vm => num 2) as f32;
+        let z = get_slot!(vm => num 3) as f32;
+        self.transform(|transform| transform.rotation = Transform::euler(x, y, z));
+    }
+
+    // accepts a vec3
+    pub fn set_scale(&self, vm: &VM) {
+        let scale = get_slot!(vm => foreign WrenVec3 => 1).0;
+        self.transform(|transform| transform.scale = scale);
+    }
+
+    pub fn set_name(&self, vm: &VM) {
+        let name = get_slot!(vm => string 1);
+        self.with_entity(|scene, entity| scene.world.insert(entity, Name(name)));
+    }
+
+    // draw a model, by its name in scene file or mesh file;
+    // returns false if there's no such model
+    pub fn set_mesh(&self, vm: &VM) {
+        let name = get_slot!(vm => string 1);
+        let set = self.with_entity(|scene, entity| match scene.find_model(&name) {
+            Some(model) => scene.world.insert(entity, MeshRenderer { model }),
+            None => false,
+        });
+        vm.set_slot_bool(0, set);
+    }
+
+    // move with a node of scene graph, by its name in scene file;
+    // returns false if there's no such node
+    pub fn attach(&self, vm: &VM) {
+        let name = get_slot!(vm => string 1);
+        let attached = self.with_entity(|scene, entity| match scene.nodes.find(&name) {
+            Some(node) => scene.world.insert(entity, SceneNode(node)),
+            None => false,
+        });
+        vm.set_slot_bool(0, attached);
+    }
+
+    // lit the scene with a light, placed at the entity
+    // and pointing along its -z
+    pub fn set_light(&self, vm: &VM) {
+        let light = get_slot!(vm => foreign Light => 1).0;
+        self.with_entity(|scene, entity| scene.world.insert(entity, light));
+    }
+
+    // view the scene from the entity, looking along its -z;
+    // accepts vertical field of view in radians. While an entity
+    // has a camera, the scene camera follows it.
+    pub fn set_camera(&self, vm: &VM) {
+        let fov = get_slot!(vm => num 1) as f32;
+        self.with_entity(|scene, entity| {
+            let camera = Camera {
+                fov,
+                ..scene.camera
+            };
+            scene.world.insert(entity, camera)
+        });
+    }
+
+    pub fn remove_mesh(&self, _vm: &VM) {
+        self.with_entity(|scene, entity| scene.world.remove::<MeshRenderer>(entity).is_some());
+    }
+
+    pub fn remove_light(&self, _vm: &VM) {
+        self.with_entity(|scene, entity| {
+            scene.world.remove::<lucien_render::Light>(entity).is_some()
+        });
+    }
+
+    pub fn remove_camera(&self, _vm: &VM) {
+        self.with_entity(|scene, entity| scene.world.remove::<Camera>(entity).is_some());
+    }
+
+    // change the transform, an entity without one gets the default first
+    fn transform<F>(&self, f: F)
+    where
+        F: FnOnce(&mut Transform),
+    {
+        self.with_entity(|scene, entity| {
+            if !scene.world.has::<Transform>(entity) {
+                scene.world.insert(entity, Transform::default());
+            }
+            scene.world.get_mut::<Transform>(entity).map(f).is_some()
+        });
+    }
+
+    // run f if scene is ready and the entity is alive, false otherwise
+    fn with_entity<F>(&self, f: F) -> bool
+    where
+        F: FnOnce(&mut Scene, Entity) -> bool,
+    {
+        with_scene(|scene| scene.world.is_alive(self.0) && f(scene, self.0)).unwrap_or(false)
+    }
+}
@@ -1,17 +1,18 @@
-use crate::vm::graphics::WrenVec3;
+use crate::vm::error::abort_init;
+use crate::vm::graphics::{with_scene, WrenVec3};
 use ruwren::{send_foreign, Class, VM};
 
-use lucien_render::PointLight;
+use lucien_render as render;
+use lucien_render::{LightId, PointLight};
+
+// A light that is not in scene yet, add it with `Graphics.add_light`
+#[derive(Debug, Clone, Copy)]
+pub struct Light(pub render::Light);
 
-#[derive(Debug)]
-pub struct Light {
-    pub position: WrenVec3,
-    pub color: WrenVec3,
-}
-// todo properly raise runtime exception in foreign method
 impl Class for Light {
-    fn initialize(_: &VM) -> Self {
-        panic!("Cannot init from wren");
+    fn initialize(vm: &VM) -> Self {
+        let light = render::Light::Point(PointLight::default());
+        abort_init(vm, "Light", Light(light))
     }
 }
 // foreign methods for wren,
@@ -20,36 +21,99 @@ impl Class for Light {
 impl Light {
     // getter that returns light position
     pub fn position(&self, vm: &VM) {
-        send_foreign!(vm, "graphics", "Vec3", self.position => 0);
+        send_foreign!(vm, "graphics", "Vec3", WrenVec3(self.0.position()) => 0);
+    }
+
+    // getter that returns light direction
+    pub fn direction(&self, vm: &VM) {
+        send_foreign!(vm, "graphics", "Vec3", WrenVec3(self.0.direction()) => 0);
     }
 
     // getter that returns light color
     pub fn color(&self, vm: &VM) {
-        send_foreign!(vm, "graphics", "Vec3", self.color => 0);
+        send_foreign!(vm, "graphics", "Vec3", WrenVec3(self.0.color()) => 0);
+    }
+
+    // getter that returns "point", "directional" or "spot"
+    pub fn kind(&self, vm: &VM) {
+        vm.set_slot_string(0, self.0.kind());
     }
 
     // getter that returns a formatted string
-    // need to be manually implemented so that the module macro can work
+    // need to be manually implemented so that the module macro can work
     pub fn fmt(&self, vm: &VM) {
         vm.set_slot_string(0, format!("{:?}", self));
     }
 }
 
-#[derive(Debug)]
-pub struct WrenPointLight(pub PointLight);
+// Handle to a point light in the live scene, get it with `Graphics.point_light(id)`
+#[derive(Debug, Clone, Copy)]
+pub struct WrenPointLight(pub LightId);
 
 impl Class for WrenPointLight {
-    fn initialize(_: &VM) -> Self {
-        panic!("Cannot init from wren");
+    fn initialize(vm: &VM) -> Self {
+        abort_init(vm, "PointLight", WrenPointLight(LightId::MAX))
     }
 }
 
+// getters return null if the light is removed, or scene is not ready
 impl WrenPointLight {
+    pub fn id(&self, vm: &VM) {
+        vm.set_slot_double(0, self.0 as f64);
+    }
+
     pub fn position(&self, vm: &VM) {
-        send_foreign!(vm, "graphics", "Vec3", WrenVec3(self.0.position) => 0);
+        match self.get(|light| light.position) {
+            Some(position) => {
+                send_foreign!(vm, "graphics", "Vec3", WrenVec3(position) => 0);
+            }
+            None => vm.set_slot_null(0),
+        }
     }
 
     pub fn color(&self, vm: &VM) {
-        send_foreign!(vm, "graphics", "Vec3", WrenVec3(self.0.color) => 0);
+        match self.get(|light| light.color) {
+            Some(color) => {
+                send_foreign!(vm, "graphics", "Vec3", WrenVec3(color) => 0);
+            }
+            None => vm.set_slot_null(0),
+        }
+    }
+
+    pub fn intensity(&self, vm: &VM) {
+        match self.get(|light| light.intensity) {
+            Some(intensity) => vm.set_slot_double(0, intensity as f64),
+            None => vm.set_slot_null(0),
+        }
+    }
+
+    // accepts a vec3
+    pub fn set_position(&self, vm: &VM) {
+        let position = get_slot!(vm => foreign WrenVec3 => 1).0;
+        self.get(|light| light.position = position);
+    }
+
+    // accepts a vec3
+    pub fn set_color(&self, vm: &VM) {
+        let color = get_slot!(vm => foreign WrenVec3 => 1).0;
+        self.get(|light| light.color = color);
+    }
+
+    // accepts a number
+    pub fn set_intensity(&self, vm: &VM) {
+        let intensity = get_slot!(vm => num 1);
+        self.get(|light| light.intensity = intensity as f32);
+    }
+
+    // run f on the point light in scene
+    fn get<F, R>(&self, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut PointLight) -> R,
+    {
+        with_scene(|scene| match scene.lights.get_mut(self.0) {
+            Some(render::Light::Point(light)) => Some(f(light)),
+            _ => None,
+        })
+        .flatten()
     }
 }
@@ -1,16 +1,483 @@
-use glam::Vec3;
-use ruwren::{Class, VM};
+use crate::vm::error::{abort, abort_argument};
+use glam::{Mat4, Quat, Vec3, Vec4};
+use lucien_render::Transform;
+use ruwren::{send_foreign, Class, VM};
+
+// Math types of graphics module, values built on glam. Operations return
+// new values, so a value handed to the scene is never changed after.
+// Angles are in degrees as in scene file, except camera fov in radians.
 
 #[derive(Debug, Clone, Copy)]
 pub struct WrenVec3(pub Vec3);
 
+#[derive(Debug, Clone, Copy)]
+pub struct WrenVec4(pub Vec4);
+
+#[derive(Debug, Clone, Copy)]
+pub struct WrenQuat(pub Quat);
+
+#[derive(Debug, Clone, Copy)]
+pub struct WrenMat4(pub Mat4);
+
+// read numbers from slot 1 on, the arguments of a constructor
+fn numbers(vm: &VM, n: usize) -> Option<Vec<f32>> {
+    (1..=n)
+        .map(|slot| vm.get_slot_double(slot).map(|v| v as f32))
+        .collect()
+}
+
+fn send_vec3(vm: &VM, v: Vec3) {
+    send_foreign!(vm, "graphics", "Vec3", WrenVec3(v) => 0);
+}
+
+fn send_vec4(vm: &VM, v: Vec4) {
+    send_foreign!(vm, "graphics", "Vec4", WrenVec4(v) => 0);
+}
+
+fn send_quat(vm: &VM, q: Quat) {
+    send_foreign!(vm, "graphics", "Quat", WrenQuat(q) => 0);
+}
+
+fn send_mat4(vm: &VM, m: Mat4) {
+    send_foreign!(vm, "graphics", "Mat4", WrenMat4(m) => 0);
+}
+
+// `Vec3.new(x, y, z)`
 impl Class for WrenVec3 {
+    fn initialize(vm: &VM) -> Self {
+        match numbers(vm, 3) {
+            Some(v) => WrenVec3(Vec3::new(v[0], v[1], v[2])),
+            None => {
+                abort(vm, "Vec3.new expects 3 numbers".to_string());
+                WrenVec3(Vec3::ZERO)
+            }
+        }
+    }
+}
+
+// `Vec4.new(x, y, z, w)`
+impl Class for WrenVec4 {
+    fn initialize(vm: &VM) -> Self {
+        match numbers(vm, 4) {
+            Some(v) => WrenVec4(Vec4::new(v[0], v[1], v[2], v[3])),
+            None => {
+                abort(vm, "Vec4.new expects 4 numbers".to_string());
+                WrenVec4(Vec4::ZERO)
+            }
+        }
+    }
+}
+
+// `Quat.new(x, y, z, w)`, from raw components;
+// `Quat.euler` and `Quat.axis_angle` are easier to use
+impl Class for WrenQuat {
+    fn initialize(vm: &VM) -> Self {
+        match numbers(vm, 4) {
+            Some(v) => WrenQuat(Quat::from_xyzw(v[0], v[1], v[2], v[3])),
+            None => {
+                abort(vm, "Quat.new expects 4 numbers".to_string());
+                WrenQuat(Quat::IDENTITY)
+            }
+        }
+    }
+}
+
+// `Mat4.new()` is identity
+impl Class for WrenMat4 {
     fn initialize(_: &VM) -> Self {
-        panic!("Cannot init from wren");
+        WrenMat4(Mat4::IDENTITY)
     }
 }
 
 impl WrenVec3 {
+    pub fn x(&self, vm: &VM) {
+        vm.set_slot_double(0, self.0.x as f64);
+    }
+
+    pub fn y(&self, vm: &VM) {
+        vm.set_slot_double(0, self.0.y as f64);
+    }
+
+    pub fn z(&self, vm: &VM) {
+        vm.set_slot_double(0, self.0.z as f64);
+    }
+
+    pub fn length(&self, vm: &VM) {
+        vm.set_slot_double(0, self.0.length() as f64);
+    }
+
+    pub fn length_squared(&self, vm: &VM) {
+        vm.set_slot_double(0, self.0.length_squared() as f64);
+    }
+
+    // zero vector stays zero
+    pub fn normalize(&self, vm: &VM) {
+        send_vec3(vm, self.0.normalize_or_zero());
+    }
+
+    pub fn neg(&self, vm: &VM) {
+        send_vec3(vm, -self.0);
+    }
+
+    pub fn add(&self, vm: &VM) {
+        let other = get_slot!(vm => foreign WrenVec3 => 1).0;
+        send_vec3(vm, self.0 + other);
+    }
+
+    pub fn sub(&self, vm: &VM) {
+        let other = get_slot!(vm => foreign WrenVec3 => 1).0;
+        send_vec3(vm, self.0 - other);
+    }
+
+    // scale by a number, or multiply component-wise
+    pub fn mul(&self, vm: &VM) {
+        if let Some(s) = vm.get_slot_double(1) {
+            return send_vec3(vm, self.0 * s as f32);
+        }
+        match vm.get_slot_foreign::<WrenVec3>(1) {
+            Some(other) => send_vec3(vm, self.0 * other.0),
+            None => abort_argument(vm, 1, "a number or Vec3"),
+        }
+    }
+
+    pub fn div(&self, vm: &VM) {
+        if let Some(s) = vm.get_slot_double(1) {
+            return send_vec3(vm, self.0 / s as f32);
+        }
+        match vm.get_slot_foreign::<WrenVec3>(1) {
+            Some(other) => send_vec3(vm, self.0 / other.0),
+            None => abort_argument(vm, 1, "a number or Vec3"),
+        }
+    }
+
+    // false for anything but a Vec3
+    pub fn eq(&self, vm: &VM) {
+        let eq = vm
+            .get_slot_foreign::<WrenVec3>(1)
+            .map(|other| other.0 == self.0);
+        vm.set_slot_bool(0, eq.unwrap_or(false));
+    }
+
+    pub fn dot(&self, vm: &VM) {
+        let other = get_slot!(vm => foreign WrenVec3 => 1).0;
+        vm.set_slot_double(0, self.0.dot(other) as f64);
+    }
+
+    pub fn cross(&self, vm: &VM) {
+        let other = get_slot!(vm => foreign WrenVec3 => 1).0;
+        send_vec3(vm, self.0.cross(other));
+    }
+
+    pub fn distance(&self, vm: &VM) {
+        let other = get_slot!(vm => foreign WrenVec3 => 1).0;
+        vm.set_slot_double(0, self.0.distance(other) as f64);
+    }
+
+    // accepts a vec3, and t in 0..1 from self to it
+    pub fn lerp(&self, vm: &VM) {
+        let other = get_slot!(vm => foreign WrenVec3 => 1).0;
+        let t = get_slot!(vm => num 2) as f32;
+        send_vec3(vm, self.0.lerp(other, t));
+    }
+
+    pub fn fmt(&self, vm: &VM) {
+        vm.set_slot_string(0, format!("{:?}", self));
+    }
+}
+
+impl WrenVec4 {
+    pub fn x(&self, vm: &VM) {
+        vm.set_slot_double(0, self.0.x as f64);
+    }
+
+    pub fn y(&self, vm: &VM) {
+        vm.set_slot_double(0, self.0.y as f64);
+    }
+
+    pub fn z(&self, vm: &VM) {
+        vm.set_slot_double(0, self.0.z as f64);
+    }
+
+    pub fn w(&self, vm: &VM) {
+        vm.set_slot_double(0, self.0.w as f64);
+    }
+
+    // x, y, z as a vec3
+    pub fn xyz(&self, vm: &VM) {
+        send_vec3(vm, self.0.truncate());
+    }
+
+    pub fn length(&self, vm: &VM) {
+        vm.set_slot_double(0, self.0.length() as f64);
+    }
+
+    pub fn length_squared(&self, vm: &VM) {
+        vm.set_slot_double(0, self.0.length_squared() as f64);
+    }
+
+    pub fn normalize(&self, vm: &VM) {
+        send_vec4(vm, self.0.normalize_or_zero());
+    }
+
+    pub fn neg(&self, vm: &VM) {
+        send_vec4(vm, -self.0);
+    }
+
+    pub fn add(&self, vm: &VM) {
+        let other = get_slot!(vm => foreign WrenVec4 => 1).0;
+        send_vec4(vm, self.0 + other);
+    }
+
+    pub fn sub(&self, vm: &VM) {
+        let other = get_slot!(vm => foreign WrenVec4 => 1).0;
+        send_vec4(vm, self.0 - other);
+    }
+
+    pub fn mul(&self, vm: &VM) {
+        if let Some(s) = vm.get_slot_double(1) {
+            return send_vec4(vm, self.0 * s as f32);
+        }
+        match vm.get_slot_foreign::<WrenVec4>(1) {
+            Some(other) => send_vec4(vm, self.0 * other.0),
+            None => abort_argument(vm, 1, "a number or Vec4"),
+        }
+    }
+
+    pub fn div(&self, vm: &VM) {
+        if let Some(s) = vm.get_slot_double(1) {
+            return send_vec4(vm, self.0 / s as f32);
+        }
+        match vm.get_slot_foreign::<WrenVec4>(1) {
+            Some(other) => send_vec4(vm, self.0 / other.0),
+            None => abort_argument(vm, 1, "a number or Vec4"),
+        }
+    }
+
+    pub fn eq(&self, vm: &VM) {
+        let eq = vm
+            .get_slot_foreign::<WrenVec4>(1)
+            .map(|other| other.0 == self.0);
+        vm.set_slot_bool(0, eq.unwrap_or(false));
+    }
+
+    pub fn dot(&self, vm: &VM) {
+        let other = get_slot!(vm => foreign WrenVec4 => 1).0;
+        vm.set_slot_double(0, self.0.dot(other) as f64);
+    }
+
+    pub fn distance(&self, vm: &VM) {
+        let other = get_slot!(vm => foreign WrenVec4 => 1).0;
+        vm.set_slot_double(0, self.0.distance(other) as f64);
+    }
+
+    pub fn lerp(&self, vm: &VM) {
+        let other = get_slot!(vm => foreign WrenVec4 => 1).0;
+        let t = get_slot!(vm => num 2) as f32;
+        send_vec4(vm, self.0.lerp(other, t));
+    }
+
+    pub fn fmt(&self, vm: &VM) {
+        vm.set_slot_string(0, format!("{:?}", self));
+    }
+}
+
+impl WrenQuat {
+    pub fn identity(vm: &VM) {
+        send_quat(vm, Quat::IDENTITY);
+    }
+
+    // euler angles in degrees, applied in y, x, z order as in scene file
+    pub fn euler(vm: &VM) {
+        let x = get_slot!(vm => num 1) as f32;
+        let y = get_slot!(vm => num 2) as f32;
+        let z = get_slot!(vm => num 3) as f32;
+        send_quat(vm, Transform::euler(x, y, z));
+    }
+
+    // rotate around a vec3 axis, by an angle in degrees
+    pub fn axis_angle(vm: &VM) {
+        let axis = get_slot!(vm => foreign WrenVec3 => 1).0;
+        let angle = get_slot!(vm => num 2) as f32;
+        let axis = axis.normalize_or_zero();
+        if axis == Vec3::ZERO {
+            return abort(vm, "rotation axis can't be zero".to_string());
+        }
+        send_quat(vm, Quat::from_axis_angle(axis, angle.to_radians()));
+    }
+
+    pub fn x(&self, vm: &VM) {
+        vm.set_slot_double(0, self.0.x as f64);
+    }
+
+    pub fn y(&self, vm: &VM) {
+        vm.set_slot_double(0, self.0.y as f64);
+    }
+
+    pub fn z(&self, vm: &VM) {
+        vm.set_slot_double(0, self.0.z as f64);
+    }
+
+    pub fn w(&self, vm: &VM) {
+        vm.set_slot_double(0, self.0.w as f64);
+    }
+
+    // rotation axis, x axis if there's no rotation
+    pub fn axis(&self, vm: &VM) {
+        send_vec3(vm, self.0.normalize().to_axis_angle().0);
+    }
+
+    // rotation angle in degrees
+    pub fn angle(&self, vm: &VM) {
+        let angle = self.0.normalize().to_axis_angle().1;
+        vm.set_slot_double(0, angle.to_degrees() as f64);
+    }
+
+    pub fn length(&self, vm: &VM) {
+        vm.set_slot_double(0, self.0.length() as f64);
+    }
+
+    pub fn normalize(&self, vm: &VM) {
+        send_quat(vm, self.0.normalize());
+    }
+
+    pub fn inverse(&self, vm: &VM) {
+        send_quat(vm, self.0.inverse());
+    }
+
+    // combine with a quat, `a * b` rotates by b then by a;
+    // or rotate a vec3
+    pub fn mul(&self, vm: &VM) {
+        if let Some(other) = vm.get_slot_foreign::<WrenQuat>(1) {
+            return send_quat(vm, self.0 * other.0);
+        }
+        match vm.get_slot_foreign::<WrenVec3>(1) {
+            Some(v) => send_vec3(vm, self.0 * v.0),
+            None => abort_argument(vm, 1, "a Quat or Vec3"),
+        }
+    }
+
+    pub fn eq(&self, vm: &VM) {
+        let eq = vm
+            .get_slot_foreign::<WrenQuat>(1)
+            .map(|other| other.0 == self.0);
+        vm.set_slot_bool(0, eq.unwrap_or(false));
+    }
+
+    pub fn dot(&self, vm: &VM) {
+        let other = get_slot!(vm => foreign WrenQuat => 1).0;
+        vm.set_slot_double(0, self.0.dot(other) as f64);
+    }
+
+    // spherical interpolation, t in 0..1 from self to the other quat
+    pub fn slerp(&self, vm: &VM) {
+        let other = get_slot!(vm => foreign WrenQuat => 1).0;
+        let t = get_slot!(vm => num 2) as f32;
+        send_quat(vm, self.0.slerp(other, t));
+    }
+
+    pub fn fmt(&self, vm: &VM) {
+        vm.set_slot_string(0, format!("{:?}", self));
+    }
+}
+
+impl WrenMat4 {
+    pub fn identity(vm: &VM) {
+        send_mat4(vm, Mat4::IDENTITY);
+    }
+
+    pub fn translation(vm: &VM) {
+        let translation = get_slot!(vm => foreign WrenVec3 => 1).0;
+        send_mat4(vm, Mat4::from_translation(translation));
+    }
+
+    pub fn rotation(vm: &VM) {
+        let rotation = get_slot!(vm => foreign WrenQuat => 1).0;
+        send_mat4(vm, Mat4::from_quat(rotation));
+    }
+
+    pub fn scale(vm: &VM) {
+        let scale = get_slot!(vm => foreign WrenVec3 => 1).0;
+        send_mat4(vm, Mat4::from_scale(scale));
+    }
+
+    // scale, then rotate, then translate, as a node transform
+    pub fn compose(vm: &VM) {
+        let translation = get_slot!(vm => foreign WrenVec3 => 1).0;
+        let rotation = get_slot!(vm => foreign WrenQuat => 2).0;
+        let scale = get_slot!(vm => foreign WrenVec3 => 3).0;
+        let m = Mat4::from_scale_rotation_translation(scale, rotation, translation);
+        send_mat4(vm, m);
+    }
+
+    // view matrix, right handed
+    pub fn look_at(vm: &VM) {
+        let eye = get_slot!(vm => foreign WrenVec3 => 1).0;
+        let target = get_slot!(vm => foreign WrenVec3 => 2).0;
+        let up = get_slot!(vm => foreign WrenVec3 => 3).0;
+        send_mat4(vm, Mat4::look_at_rh(eye, target, up));
+    }
+
+    // projection matrix, accepts vertical fov in radians as camera
+    pub fn perspective(vm: &VM) {
+        let fov = get_slot!(vm => num 1) as f32;
+        let aspect_ratio = get_slot!(vm => num 2) as f32;
+        let z_near = get_slot!(vm => num 3) as f32;
+        let z_far = get_slot!(vm => num 4) as f32;
+        send_mat4(vm, Mat4::perspective_rh(fov, aspect_ratio, z_near, z_far));
+    }
+
+    // column by index in 0..4, as a vec4
+    pub fn col(&self, vm: &VM) {
+        let index = get_slot!(vm => num 1);
+        if !(0.0..4.0).contains(&index) {
+            return abort(vm, format!("column {} is out of 0..4", index));
+        }
+        send_vec4(vm, self.0.col(index as usize));
+    }
+
+    pub fn determinant(&self, vm: &VM) {
+        vm.set_slot_double(0, self.0.determinant() as f64);
+    }
+
+    // a matrix that can't be inverted gives a matrix of nan
+    pub fn inverse(&self, vm: &VM) {
+        send_mat4(vm, self.0.inverse());
+    }
+
+    pub fn transpose(&self, vm: &VM) {
+        send_mat4(vm, self.0.transpose());
+    }
+
+    // combine with a mat4, `a * b` applies b then a; or transform a vec4
+    pub fn mul(&self, vm: &VM) {
+        if let Some(other) = vm.get_slot_foreign::<WrenMat4>(1) {
+            return send_mat4(vm, self.0 * other.0);
+        }
+        match vm.get_slot_foreign::<WrenVec4>(1) {
+            Some(v) => send_vec4(vm, self.0 * v.0),
+            None => abort_argument(vm, 1, "a Mat4 or Vec4"),
+        }
+    }
+
+    pub fn eq(&self, vm: &VM) {
+        let eq = vm
+            .get_slot_foreign::<WrenMat4>(1)
+            .map(|other| other.0 == self.0);
+        vm.set_slot_bool(0, eq.unwrap_or(false));
+    }
+
+    // transform a vec3 position, translation applies
+    pub fn transform_point(&self, vm: &VM) {
+        let point = get_slot!(vm => foreign WrenVec3 => 1).0;
+        send_vec3(vm, self.0.transform_point3(point));
+    }
+
+    // transform a vec3 direction, translation doesn't apply
+    pub fn transform_vector(&self, vm: &VM) {
+        let vector = get_slot!(vm => foreign WrenVec3 => 1).0;
+        send_vec3(vm, self.0.transform_vector3(vector));
+    }
+
     pub fn fmt(&self, vm: &VM) {
         vm.set_slot_string(0, format!("{:?}", self));
     }
@@ -1,18 +1,52 @@
 pub mod math;
-pub use math::WrenVec3;
+pub use math::*;
 
 pub mod light;
 pub use light::*;
 
+pub mod camera;
+pub use camera::*;
+
+pub mod model;
+pub use model::*;
+
+pub mod entity;
+pub use entity::*;
+
+use crate::vm::error::abort_init;
 use glam::vec3;
-use ruwren::{get_slot_checked, send_foreign, Class, VM};
+use lazy_static::lazy_static;
+use lucien_render as render;
+use lucien_render::{Scene, SharedScene};
+use ruwren::{send_foreign, Class, VM};
+use std::sync::Mutex;
+
+lazy_static! {
+    // live scene that the renderer draws, so foreign methods can change it
+    static ref SCENE: Mutex<Option<SharedScene>> = Mutex::new(None);
+}
+
+// bind the scene rendered by backend
+pub fn bind_scene(scene: SharedScene) {
+    *SCENE.lock().unwrap() = Some(scene);
+}
+
+// run f on the bound scene, None if no scene is bound yet
+pub fn with_scene<F, R>(f: F) -> Option<R>
+where
+    F: FnOnce(&mut Scene) -> R,
+{
+    let lock = SCENE.lock().unwrap();
+    let shared = lock.as_ref()?;
+    let mut scene = shared.lock().unwrap();
+    Some(f(&mut scene))
+}
 
 pub struct Graphics;
 
-// todo properly raise runtime exception in foreign method
 impl Class for Graphics {
-    fn initialize(_: &VM) -> Self {
-        panic!("Graphics is a purely static class");
+    fn initialize(vm: &VM) -> Self {
+        abort_init(vm, "Graphics", Graphics)
     }
 }
 
@@ -20,21 +54,121 @@ impl Graphics {
     // create a vector3
     // accepts 3 numbers as params
     pub fn new_vec3(vm: &VM) {
-        let x = get_slot_checked!(vm => num 1);
-        let y = get_slot_checked!(vm => num 2);
-        let z = get_slot_checked!(vm => num 3);
+        let x = get_slot!(vm => num 1);
+        let y = get_slot!(vm => num 2);
+        let z = get_slot!(vm => num 3);
         let vec = WrenVec3(vec3(x as f32, y as f32, z as f32));
 
         send_foreign!(vm, "graphics", "Vec3", vec => 0);
     }
 
-    // create a point light in current scene
+    // create a point light, add it to scene with `add_light`
     // accepts 2 vec3 as params
     pub fn new_light(vm: &VM) {
-        let position = *get_slot_checked!(vm => foreign WrenVec3 => 1);
-        let color = *get_slot_checked!(vm => foreign WrenVec3 => 2);
+        let position = get_slot!(vm => foreign WrenVec3 => 1).0;
+        let color = get_slot!(vm => foreign WrenVec3 => 2).0;
+        let light = render::Light::Point(render::PointLight::new(position, color));
+
+        send_foreign!(vm, "graphics", "Light", Light(light) => 0);
+    }
+
+    // create a directional light
+    // accepts 2 vec3 as params
+    pub fn new_directional_light(vm: &VM) {
+        let direction = get_slot!(vm => foreign WrenVec3 => 1).0;
+        let color = get_slot!(vm => foreign WrenVec3 => 2).0;
+        let light = render::Light::Directional(render::DirectionalLight::new(direction, color));
+
+        send_foreign!(vm, "graphics", "Light", Light(light) => 0);
+    }
+
+    // create a spot light
+    // accepts 3 vec3 and the cone angle in radians as params
+    pub fn new_spot_light(vm: &VM) {
+        let position = get_slot!(vm => foreign WrenVec3 => 1).0;
+        let direction = get_slot!(vm => foreign WrenVec3 => 2).0;
+        let color = get_slot!(vm => foreign WrenVec3 => 3).0;
+        let angle = get_slot!(vm => num 4);
+        let light = render::SpotLight::new(position, direction, color, angle as f32);
+
+        send_foreign!(vm, "graphics", "Light", Light(render::Light::Spot(light)) => 0);
+    }
+
+    // add a light to current scene,
+    // returns light id, or null if scene is not ready or full of lights
+    pub fn add_light(vm: &VM) {
+        let light = get_slot!(vm => foreign Light => 1).0;
+        match with_scene(|scene| scene.lights.add(light)).flatten() {
+            Some(id) => vm.set_slot_double(0, id as f64),
+            None => vm.set_slot_null(0),
+        }
+    }
+
+    // remove a light from current scene by id,
+    // returns true if the light was removed
+    pub fn remove_light(vm: &VM) {
+        let id = get_slot!(vm => num 1) as render::LightId;
+        let removed = with_scene(|scene| scene.lights.remove(id).is_some()).unwrap_or(false);
+        vm.set_slot_bool(0, removed);
+    }
+
+    // returns the camera of current scene
+    pub fn camera(vm: &VM) {
+        send_foreign!(vm, "graphics", "Camera", WrenCamera => 0);
+    }
+
+    // returns the point light by id,
+    // or null if there's no such point light
+    pub fn point_light(vm: &VM) {
+        let id = get_slot!(vm => num 1) as render::LightId;
+        let is_point =
+            with_scene(|scene| matches!(scene.lights.get(id), Some(render::Light::Point(_))))
+                .unwrap_or(false);
+        if is_point {
+            send_foreign!(vm, "graphics", "PointLight", WrenPointLight(id) => 0);
+        } else {
+            vm.set_slot_null(0);
+        }
+    }
+
+    // returns the model by name in scene file,
+    // or null if there's no such model
+    pub fn model(vm: &VM) {
+        let name = get_slot!(vm => string 1);
+        match with_scene(|scene| scene.nodes.find(&name)).flatten() {
+            Some(node) => {
+                send_foreign!(vm, "graphics", "Model", WrenModel(node) => 0);
+            }
+            None => vm.set_slot_null(0),
+        }
+    }
+
+    // spawn an entity with a default transform at world origin,
+    // returns null if scene is not ready
+    pub fn spawn(vm: &VM) {
+        let entity = with_scene(|scene| {
+            let entity = scene.world.spawn();
+            scene.world.insert(entity, render::Transform::default());
+            entity
+        });
+        match entity {
+            Some(entity) => {
+                send_foreign!(vm, "graphics", "Entity", WrenEntity(entity) => 0);
+            }
+            None => vm.set_slot_null(0),
+        }
+    }
 
-        send_foreign!(vm, "graphics", "Light", Light { position, color } => 0);
+    // returns the first entity with the name,
+    // or null if there's no such entity
+    pub fn entity(vm: &VM) {
+        let name = get_slot!(vm => string 1);
+        match with_scene(|scene| scene.find_entity(&name)).flatten() {
+            Some(entity) => {
+                send_foreign!(vm, "graphics", "Entity", WrenEntity(entity) => 0);
+            }
+            None => vm.set_slot_null(0),
+        }
     }
 
     // pub fn new_point_light(_vm: &VM) {
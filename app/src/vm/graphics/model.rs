@@ -0,0 +1,84 @@
+use crate::vm::error::abort_init;
+use crate::vm::graphics::{with_scene, WrenVec3};
+use lucien_render::{NodeId, Transform};
+use ruwren::{send_foreign, Class, VM};
+
+// Handle to a node of the live scene, models attached to the node
+// move with it. Get it with `Graphics.model(name)`.
+#[derive(Debug, Clone, Copy)]
+pub struct WrenModel(pub NodeId);
+
+impl Class for WrenModel {
+    fn initialize(vm: &VM) -> Self {
+        abort_init(vm, "Model", WrenModel(NodeId::MAX))
+    }
+}
+
+// getters return null if scene is not ready
+impl WrenModel {
+    pub fn name(&self, vm: &VM) {
+        let name = with_scene(|scene| scene.nodes.get(self.0).map(|node| node.name.clone()));
+        match name.flatten() {
+            Some(name) => vm.set_slot_string(0, name),
+            None => vm.set_slot_null(0),
+        }
+    }
+
+    // position relative to parent
+    pub fn position(&self, vm: &VM) {
+        let position = with_scene(|scene| {
+            scene
+                .nodes
+                .get(self.0)
+                .map(|node| node.transform.translation)
+        });
+        match position.flatten() {
+            Some(position) => {
+                send_foreign!(vm, "graphics", "Vec3", WrenVec3(position) => 0);
+            }
+            None => vm.set_slot_null(0),
+        }
+    }
+
+    pub fn scale(&self, vm: &VM) {
+        let scale = with_scene(|scene| scene.nodes.get(self.0).map(|node| node.transform.scale));
+        match scale.flatten() {
+            Some(scale) => {
+                send_foreign!(vm, "graphics", "Vec3", WrenVec3(scale) => 0);
+            }
+            None => vm.set_slot_null(0),
+        }
+    }
+
+    // accepts a vec3
+    pub fn set_position(&self, vm: &VM) {
+        let position = get_slot!(vm => foreign WrenVec3 => 1).0;
+        self.update(|transform| transform.translation = position);
+    }
+
+    // accepts euler angles in degrees, applied in y, x, z order as in scene file
+    pub fn set_rotation(&self, vm: &VM) {
+        let x = get_slot!(vm => num 1) as f32;
+        let y = get_slot!(vm => num 2) as f32;
+        let z = get_slot!(vm => num 3) as f32;
+        self.update(|transform| transform.rotation = Transform::euler(x, y, z));
+    }
+
+    // accepts a vec3
+    pub fn set_scale(&self, vm: &VM) {
+        let scale = get_slot!(vm => foreign WrenVec3 => 1).0;
+        self.update(|transform| transform.scale = scale);
+    }
+
+    // change local transform, world matrix is updated before render
+    fn update<F>(&self, f: F)
+    where
+        F: FnOnce(&mut Transform),
+    {
+        with_scene(|scene| {
+            if let Some(node) = scene.nodes.get_mut(self.0) {
+                f(&mut node.transform);
+            }
+        });
+    }
+}
@@ -0,0 +1,89 @@
+use crate::vm::error::abort_init;
+use lazy_static::lazy_static;
+use lucien_core::input::{ActionBindings, Input, InputEvent};
+use ruwren::{Class, VM};
+use std::sync::Mutex;
+
+lazy_static! {
+    // input of current frame, scripts read it on update
+    static ref INPUT: Mutex<Input> = Mutex::new(Input::default());
+}
+
+// reset input with the actions of a project
+pub fn reset_input(actions: &ActionBindings) {
+    *INPUT.lock().unwrap() = Input::new(actions.clone());
+}
+
+pub fn handle_input(event: InputEvent) {
+    INPUT.lock().unwrap().handle(event);
+}
+
+// call after scripts update
+pub fn end_input_frame() {
+    INPUT.lock().unwrap().end_frame();
+}
+
+pub struct WrenInput;
+
+impl Class for WrenInput {
+    fn initialize(vm: &VM) -> Self {
+        abort_init(vm, "Input", WrenInput)
+    }
+}
+
+// static methods for wren, buttons and actions are strings
+impl WrenInput {
+    pub fn pressed(vm: &VM) {
+        let button = get_slot!(vm => string 1);
+        vm.set_slot_bool(0, INPUT.lock().unwrap().pressed(&button));
+    }
+
+    pub fn just_pressed(vm: &VM) {
+        let button = get_slot!(vm => string 1);
+        vm.set_slot_bool(0, INPUT.lock().unwrap().just_pressed(&button));
+    }
+
+    pub fn released(vm: &VM) {
+        let button = get_slot!(vm => string 1);
+        vm.set_slot_bool(0, INPUT.lock().unwrap().released(&button));
+    }
+
+    pub fn action_pressed(vm: &VM) {
+        let action = get_slot!(vm => string 1);
+        vm.set_slot_bool(0, INPUT.lock().unwrap().action_pressed(&action));
+    }
+
+    pub fn action_just_pressed(vm: &VM) {
+        let action = get_slot!(vm => string 1);
+        vm.set_slot_bool(0, INPUT.lock().unwrap().action_just_pressed(&action));
+    }
+
+    pub fn action_released(vm: &VM) {
+        let action = get_slot!(vm => string 1);
+        vm.set_slot_bool(0, INPUT.lock().unwrap().action_released(&action));
+    }
+
+    pub fn mouse_x(vm: &VM) {
+        vm.set_slot_double(0, INPUT.lock().unwrap().cursor().0 as f64);
+    }
+
+    pub fn mouse_y(vm: &VM) {
+        vm.set_slot_double(0, INPUT.lock().unwrap().cursor().1 as f64);
+    }
+
+    pub fn mouse_dx(vm: &VM) {
+        vm.set_slot_double(0, INPUT.lock().unwrap().mouse_delta().0 as f64);
+    }
+
+    pub fn mouse_dy(vm: &VM) {
+        vm.set_slot_double(0, INPUT.lock().unwrap().mouse_delta().1 as f64);
+    }
+
+    pub fn scroll_x(vm: &VM) {
+        vm.set_slot_double(0, INPUT.lock().unwrap().scroll().0 as f64);
+    }
+
+    pub fn scroll_y(vm: &VM) {
+        vm.set_slot_double(0, INPUT.lock().unwrap().scroll().1 as f64);
+    }
+}
@@ -1,5 +1,13 @@
+// macros first, so modules below can use them
+#[macro_use]
+pub mod error;
+
+pub mod assets;
+pub mod behaviour;
 pub mod graphics;
+pub mod input;
 pub mod printer;
+pub mod time;
 
 mod scripting;
 pub use scripting::*;
@@ -1,67 +1,283 @@
+use crate::vm::behaviour::Behaviours;
 use crate::vm::printer::LogPrinter;
 use anyhow::{anyhow, Context, Result};
 use lucien_core::logger::logger;
-use lucien_core::resources::Project;
+use lucien_core::resources::{assets, Project, ScriptSource};
+use lucien_core::scheduler::Frame;
+use lucien_core::time::Clock;
+use lucien_render::SharedScene;
 use ruwren::{
-    create_module, BasicFileLoader, FunctionSignature, Handle, ModuleLibrary, ModuleScriptLoader,
-    VMConfig, VMWrapper,
+    create_module, FunctionSignature, Handle, ModuleLibrary, ModuleScriptLoader, VMConfig,
+    VMWrapper,
 };
-use slog::{error, info};
+use slog::{error, info, warn};
+use std::cell::RefCell;
 use std::rc::Rc;
 
 static DEFAULT_SCRIPT: &str = r##"
 var start = Fn.new {
     System.print("No start function defined!")
 }
-var update = Fn.new { }
+var update = Fn.new { |dt| }
 "##;
 
+// Load wren modules relative to project root through the asset server,
+// from the resource loader of the project
+struct ScriptLoader;
+
+impl ModuleScriptLoader for ScriptLoader {
+    fn load_script(&mut self, name: String) -> Option<String> {
+        let path = format!("{}.wren", name);
+        match assets().load::<ScriptSource>(&path) {
+            Ok(source) => Some(source.0.clone()),
+            Err(e) => {
+                warn!(logger(), "* [wren] {:#}", e);
+                None
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Scripting {
     vm: VMWrapper,
     src: String,
+    // entry module name, from project manifest
+    entry: String,
+    // behaviours attached to entities, shared by clones of the vm
+    behaviours: Rc<RefCell<Behaviours>>,
 }
 
 // we must create wren modules here because they are private
 static GRAPHICS_MODULE_SRC: &str = include_str!("wren/graphics.wren");
+static TIME_MODULE_SRC: &str = include_str!("wren/time.wren");
+static INPUT_MODULE_SRC: &str = include_str!("wren/input.wren");
+static BEHAVIOUR_MODULE_SRC: &str = include_str!("wren/behaviour.wren");
+static ASSETS_MODULE_SRC: &str = include_str!("wren/assets.wren");
 
 create_module!(
     class("Vec3") crate::vm::graphics::WrenVec3 => vec3 {
+        instance(getter "x") x,
+        instance(getter "y") y,
+        instance(getter "z") z,
+        instance(getter "length") length,
+        instance(getter "length_squared") length_squared,
+        instance(getter "normalize") normalize,
+        instance(getter "-") neg,
+        instance(fn "+", 1) add,
+        instance(fn "-", 1) sub,
+        instance(fn "*", 1) mul,
+        instance(fn "/", 1) div,
+        instance(fn "==", 1) eq,
+        instance(fn "dot", 1) dot,
+        instance(fn "cross", 1) cross,
+        instance(fn "distance", 1) distance,
+        instance(fn "lerp", 2) lerp,
+        instance(getter "fmt") fmt
+    }
+
+    class("Vec4") crate::vm::graphics::WrenVec4 => vec4 {
+        instance(getter "x") x,
+        instance(getter "y") y,
+        instance(getter "z") z,
+        instance(getter "w") w,
+        instance(getter "xyz") xyz,
+        instance(getter "length") length,
+        instance(getter "length_squared") length_squared,
+        instance(getter "normalize") normalize,
+        instance(getter "-") neg,
+        instance(fn "+", 1) add,
+        instance(fn "-", 1) sub,
+        instance(fn "*", 1) mul,
+        instance(fn "/", 1) div,
+        instance(fn "==", 1) eq,
+        instance(fn "dot", 1) dot,
+        instance(fn "distance", 1) distance,
+        instance(fn "lerp", 2) lerp,
+        instance(getter "fmt") fmt
+    }
+
+    class("Quat") crate::vm::graphics::WrenQuat => quat {
+        static(fn "identity", 0) identity,
+        static(fn "euler", 3) euler,
+        static(fn "axis_angle", 2) axis_angle,
+        instance(getter "x") x,
+        instance(getter "y") y,
+        instance(getter "z") z,
+        instance(getter "w") w,
+        instance(getter "axis") axis,
+        instance(getter "angle") angle,
+        instance(getter "length") length,
+        instance(getter "normalize") normalize,
+        instance(getter "inverse") inverse,
+        instance(fn "*", 1) mul,
+        instance(fn "==", 1) eq,
+        instance(fn "dot", 1) dot,
+        instance(fn "slerp", 2) slerp,
+        instance(getter "fmt") fmt
+    }
+
+    class("Mat4") crate::vm::graphics::WrenMat4 => mat4 {
+        static(fn "identity", 0) identity,
+        static(fn "translation", 1) translation,
+        static(fn "rotation", 1) rotation,
+        static(fn "scale", 1) scale,
+        static(fn "compose", 3) compose,
+        static(fn "look_at", 3) look_at,
+        static(fn "perspective", 4) perspective,
+        instance(fn "col", 1) col,
+        instance(getter "determinant") determinant,
+        instance(getter "inverse") inverse,
+        instance(getter "transpose") transpose,
+        instance(fn "*", 1) mul,
+        instance(fn "==", 1) eq,
+        instance(fn "transform_point", 1) transform_point,
+        instance(fn "transform_vector", 1) transform_vector,
         instance(getter "fmt") fmt
     }
 
     class("Light") crate::vm::graphics::Light => light {
         instance(getter "position") position,
+        instance(getter "direction") direction,
         instance(getter "color") color,
+        instance(getter "kind") kind,
         instance(getter "fmt") fmt
     }
 
     class("PointLight") crate::vm::graphics::WrenPointLight => point_light {
+        instance(getter "id") id,
+        instance(getter "position") position,
+        instance(getter "color") color,
+        instance(getter "intensity") intensity,
+        instance(fn "set_position", 1) set_position,
+        instance(fn "set_color", 1) set_color,
+        instance(fn "set_intensity", 1) set_intensity
+    }
+
+    class("Camera") crate::vm::graphics::WrenCamera => camera {
+        instance(getter "eye") eye,
+        instance(getter "look_at") look_at,
+        instance(getter "fov") fov,
+        instance(fn "set_eye", 1) set_eye,
+        instance(fn "set_look_at", 1) set_look_at,
+        instance(fn "set_fov", 1) set_fov
+    }
+
+    class("Model") crate::vm::graphics::WrenModel => model {
+        instance(getter "name") name,
         instance(getter "position") position,
-        instance(getter "color") color
+        instance(getter "scale") scale,
+        instance(fn "set_position", 1) set_position,
+        instance(fn "set_rotation", 3) set_rotation,
+        instance(fn "set_scale", 1) set_scale
+    }
+
+    class("Entity") crate::vm::graphics::WrenEntity => entity {
+        instance(getter "id") id,
+        instance(getter "alive") alive,
+        instance(getter "position") position,
+        instance(fn "despawn", 0) despawn,
+        instance(fn "set_position", 1) set_position,
+        instance(fn "set_rotation", 3) set_rotation,
+        instance(fn "set_scale", 1) set_scale,
+        instance(fn "set_name", 1) set_name,
+        instance(fn "set_mesh", 1) set_mesh,
+        instance(fn "attach", 1) attach,
+        instance(fn "set_light", 1) set_light,
+        instance(fn "set_camera", 1) set_camera,
+        instance(fn "remove_mesh", 0) remove_mesh,
+        instance(fn "remove_light", 0) remove_light,
+        instance(fn "remove_camera", 0) remove_camera
     }
 
     class("Graphics") crate::vm::graphics::Graphics => cg {
         static(fn "new_vec3", 3) new_vec3,
-        static(fn "new_light", 2) new_light
+        static(fn "new_light", 2) new_light,
+        static(fn "new_directional_light", 2) new_directional_light,
+        static(fn "new_spot_light", 4) new_spot_light,
+        static(fn "add_light", 1) add_light,
+        static(fn "remove_light", 1) remove_light,
+        static(fn "camera", 0) camera,
+        static(fn "point_light", 1) point_light,
+        static(fn "model", 1) model,
+        static(fn "spawn", 0) spawn,
+        static(fn "entity", 1) entity
     }
 
     module => graphics
 );
 
+create_module!(
+    class("Time") crate::vm::time::WrenTime => wren_time {
+        static(fn "elapsed", 0) elapsed,
+        static(fn "delta", 0) delta,
+        static(fn "frame", 0) frame,
+        static(fn "fixed_step", 0) fixed_step,
+        static(fn "fixed_update", 0) fixed_update,
+        static(fn "alpha", 0) alpha
+    }
+
+    module => time
+);
+
+create_module!(
+    class("Input") crate::vm::input::WrenInput => wren_input {
+        static(fn "pressed", 1) pressed,
+        static(fn "just_pressed", 1) just_pressed,
+        static(fn "released", 1) released,
+        static(fn "action_pressed", 1) action_pressed,
+        static(fn "action_just_pressed", 1) action_just_pressed,
+        static(fn "action_released", 1) action_released,
+        static(fn "mouse_x", 0) mouse_x,
+        static(fn "mouse_y", 0) mouse_y,
+        static(fn "mouse_dx", 0) mouse_dx,
+        static(fn "mouse_dy", 0) mouse_dy,
+        static(fn "scroll_x", 0) scroll_x,
+        static(fn "scroll_y", 0) scroll_y
+    }
+
+    module => input
+);
+
+create_module!(
+    class("Assets") crate::vm::assets::WrenAssets => wren_assets {
+        static(fn "load", 1) load,
+        static(fn "state", 1) state,
+        static(fn "error", 1) error,
+        static(fn "pending", 0) pending,
+        static(fn "release", 1) release
+    }
+
+    module => assets
+);
+
 // interpret and get update and start function;
 // use default empty function if nothing;
 // update camera, scene light position;
 impl Scripting {
     pub fn new(project: &Project) -> Result<Self> {
-        let root = project.path("").unwrap();
-        let mut loader = BasicFileLoader::new().base_dir(root);
+        let entry = project.manifest().entry.clone();
+        let scripting = Scripting::build(entry)?;
+        // fixed steps run at fixed update rate
+        let step = project.manifest().loop_config().update_step();
+        crate::vm::time::reset_time(step.as_secs_f64());
+        crate::vm::input::reset_input(&project.manifest().actions);
+
+        Ok(scripting)
+    }
+
+    // create a vm with engine modules, and read the entry script
+    fn build(entry: String) -> Result<Self> {
+        let mut loader = ScriptLoader;
         let src = loader
-            .load_script(String::from("main"))
+            .load_script(entry.clone())
             .unwrap_or(DEFAULT_SCRIPT.to_string());
 
         let mut lib = ModuleLibrary::new();
         graphics::publish_module(&mut lib);
+        time::publish_module(&mut lib);
+        input::publish_module(&mut lib);
+        assets::publish_module(&mut lib);
 
         let vm = VMConfig::new()
             .enable_relative_import(true)
@@ -72,64 +288,330 @@ impl Scripting {
 
         vm.interpret("graphics", GRAPHICS_MODULE_SRC)
             .context("Failed to load wren module")?;
+        vm.interpret("time", TIME_MODULE_SRC)
+            .context("Failed to load wren module")?;
+        vm.interpret("input", INPUT_MODULE_SRC)
+            .context("Failed to load wren module")?;
+        vm.interpret("behaviour", BEHAVIOUR_MODULE_SRC)
+            .context("Failed to load wren module")?;
+        vm.interpret("assets", ASSETS_MODULE_SRC)
+            .context("Failed to load wren module")?;
+        let behaviours = Rc::new(RefCell::new(Behaviours::new(&vm)));
 
-        Ok(Self { vm, src })
+        Ok(Self {
+            vm,
+            src,
+            entry,
+            behaviours,
+        })
     }
 
     pub fn as_ref(&mut self) -> &Self {
         &*self
     }
 
-    // reload script
-    // todo hotload
+    // share the rendered scene with foreign methods
+    pub fn bind_scene(&self, scene: SharedScene) {
+        crate::vm::graphics::bind_scene(scene);
+    }
+
     pub fn init(&self) {
-        match self.vm.interpret("main", &self.src) {
-            Ok(_) => {}
-            Err(e) => {
-                error!(logger(), "{}", e);
-            }
-        };
-        info!(logger(), "script loaded");
+        match self.interpret() {
+            Ok(_) => info!(logger(), "script loaded"),
+            Err(e) => error!(logger(), "{:?}", e),
+        }
+    }
+
+    fn interpret(&self) -> Result<()> {
+        self.vm
+            .interpret(&self.entry, &self.src)
+            .map_err(|e| anyhow!("{}", e))
+            .with_context(|| format!("Failed to interpret {}.wren", self.entry))
     }
 
-    // find `start` fn in main.wren
+    // load the scripts again in a new vm and call `reload` fn;
+    // scene and time are kept, they live outside of the vm.
+    // On error the new vm is dropped, so the caller can keep
+    // running the current one. Behaviours of the current vm are
+    // destroyed once `reload` succeeds, they're attached again by it.
+    pub fn reload(&self) -> Result<Self> {
+        let scripting = Scripting::build(self.entry.clone())?;
+        scripting.interpret()?;
+        scripting
+            .call(scripting.reload_fn())
+            .context("Failed to call reload function")?;
+        self.behaviours.borrow_mut().destroy(&self.vm);
+        Ok(scripting)
+    }
+
+    // find `start` fn in entry script
     pub fn start_fn(&self) -> Option<Rc<Handle>> {
-        self.vm.execute(|vm| {
-            if vm.has_variable("main", "start") {
-                vm.ensure_slots(1);
-                vm.get_variable("main", "start", 0);
-                Some(self.vm.get_slot_handle(0))
-            } else {
-                error!(logger(), "script doesn't have `start` method");
-                None
-            }
-        })
+        let handle = self.variable("start");
+        if handle.is_none() {
+            error!(logger(), "script doesn't have `start` method");
+        }
+        handle
     }
 
-    // find `update` fn in main.wren
+    // find `update` fn in entry script
     pub fn update_fn(&self) -> Option<Rc<Handle>> {
+        let handle = self.variable("update");
+        if handle.is_none() {
+            error!(logger(), "script doesn't have `update` method");
+        }
+        handle
+    }
+
+    // find optional `fixed_update` fn in entry script
+    pub fn fixed_update_fn(&self) -> Option<Rc<Handle>> {
+        self.variable("fixed_update")
+    }
+
+    // find optional `reload` fn in entry script, it's called
+    // instead of `start` when the script is reloaded
+    pub fn reload_fn(&self) -> Option<Rc<Handle>> {
+        self.variable("reload")
+    }
+
+    // handle to a top level variable of entry script
+    fn variable(&self, name: &str) -> Option<Rc<Handle>> {
         self.vm.execute(|vm| {
-            if vm.has_variable("main", "update") {
+            if vm.has_variable(&self.entry, name) {
                 vm.ensure_slots(1);
-                vm.get_variable("main", "update", 0);
+                vm.get_variable(&self.entry, name, 0);
                 Some(self.vm.get_slot_handle(0))
             } else {
-                error!(logger(), "script doesn't have `update` method");
                 None
             }
         })
     }
 
+    // call a function found by `start_fn` or `update_fn`,
+    // nothing happens if the function is not defined
     pub fn call(&self, handle: Option<Rc<Handle>>) -> Result<()> {
+        self.call_with(handle, &[])
+    }
+
+    // tick time with the clock, and call `update` with delta time,
+    // then behaviours; a failing behaviour doesn't fail the update
+    pub fn update(&self, handle: Option<Rc<Handle>>, clock: &dyn Clock) -> Result<()> {
+        let dt = crate::vm::time::tick_time(clock);
+        let res = self.call_with(handle, &[dt]);
+        self.behaviours.borrow_mut().update(&self.vm, dt);
+        res
+    }
+
+    // fixed steps of the frame, seen by `Time.fixed_update` and `Time.alpha`
+    pub fn schedule(&self, frame: &Frame) {
+        crate::vm::time::schedule_time(frame);
+    }
+
+    // call `fixed_update` with the fixed step in seconds
+    pub fn fixed_update(&self, handle: Option<Rc<Handle>>, step: f64) -> Result<()> {
+        self.call_with(handle, &[step])
+    }
+
+    // call a function with numbers as arguments
+    fn call_with(&self, handle: Option<Rc<Handle>>, args: &[f64]) -> Result<()> {
+        let handle = match handle {
+            Some(handle) => handle,
+            None => return Ok(()),
+        };
         let fn_call = self
             .vm
-            .make_call_handle(FunctionSignature::new_function("call", 0));
-        self.vm.set_slot_handle(0, handle.as_ref().unwrap());
+            .make_call_handle(FunctionSignature::new_function("call", args.len()));
+        self.vm.execute(|vm| vm.ensure_slots(args.len() + 1));
+        self.vm.set_slot_handle(0, &handle);
+        self.vm.execute(|vm| {
+            for (i, arg) in args.iter().enumerate() {
+                vm.set_slot_double(i + 1, *arg);
+            }
+        });
+        // a runtime error has the stack trace of the fiber
         let res = self.vm.call_handle(&fn_call);
         if let Err(e) = res {
             error!(logger(), "* [wren] {}", e);
-            return Err(anyhow!("wren runtime error"));
+            return Err(anyhow!("{}", e).context("wren runtime error"));
         };
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lazy_static::lazy_static;
+    use lucien_core::resources::{init_loader_with, MemoryLoader};
+    use lucien_core::time::ManualClock;
+    use std::time::Duration;
+
+    lazy_static! {
+        // scripts of tests, each test has its own entry module
+        static ref SCRIPTS: MemoryLoader = MemoryLoader::new();
+    }
+
+    // load the entry script from memory
+    fn load(entry: &str, src: &str) -> Scripting {
+        init_loader_with(&*SCRIPTS);
+        let path = format!("{}.wren", entry);
+        SCRIPTS.insert(&path, src);
+        let scripting = Scripting::build(entry.to_string()).unwrap();
+        scripting.interpret().unwrap();
+        SCRIPTS.remove(&path);
+        scripting
+    }
+
+    fn update(scripting: &Scripting) -> Result<()> {
+        let clock = ManualClock::new();
+        clock.advance(Duration::from_millis(16));
+        scripting.update(scripting.update_fn(), &clock)
+    }
+
+    // a top level number of entry script
+    fn number(scripting: &Scripting, name: &str) -> Option<f64> {
+        scripting.vm.execute(|vm| {
+            vm.ensure_slots(1);
+            vm.get_variable(&scripting.entry, name, 0);
+            vm.get_slot_double(0)
+        })
+    }
+
+    #[test]
+    fn engine_runs_on_after_runtime_error() {
+        let scripting = load(
+            "runtime_error",
+            r#"
+var frames = 0
+var update = Fn.new { |dt|
+  frames = frames + 1
+  if (frames == 1) Fiber.abort("first frame fails")
+}
+"#,
+        );
+        assert!(update(&scripting).is_err());
+        assert!(update(&scripting).is_ok());
+        assert_eq!(number(&scripting, "frames"), Some(2.0));
+    }
+
+    #[test]
+    fn runtime_error_has_stack_trace() {
+        let scripting = load(
+            "stack_trace",
+            r#"
+class Bomb {
+  static explode() {
+    Fiber.abort("boom")
+  }
+}
+var update = Fn.new { |dt| Bomb.explode() }
+"#,
+        );
+        let error = format!("{:#}", update(&scripting).unwrap_err());
+        assert!(error.contains("boom"), "{}", error);
+        assert!(error.contains("explode"), "{}", error);
+        assert!(error.contains("stack_trace"), "{}", error);
+    }
+
+    #[test]
+    fn wrong_argument_aborts_fiber() {
+        let scripting = load(
+            "wrong_argument",
+            r#"
+import "graphics" for Graphics
+var update = Fn.new { |dt| Graphics.new_vec3("one", 2, 3) }
+"#,
+        );
+        let error = format!("{:#}", update(&scripting).unwrap_err());
+        assert!(
+            error.contains("expected a number as argument 1"),
+            "{}",
+            error
+        );
+        let error = format!("{:#}", update(&scripting).unwrap_err());
+        assert!(
+            error.contains("expected a number as argument 1"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn wrong_foreign_argument_aborts_fiber() {
+        let scripting = load(
+            "wrong_foreign",
+            r#"
+import "graphics" for Graphics
+var update = Fn.new { |dt|
+  var color = Graphics.new_vec3(1, 1, 1)
+  Graphics.new_light(color, "red")
+}
+"#,
+        );
+        let error = format!("{:#}", update(&scripting).unwrap_err());
+        assert!(error.contains("expected Vec3 as argument 2"), "{}", error);
+    }
+
+    #[test]
+    fn engine_classes_cant_be_created() {
+        let scripting = load(
+            "create_engine_class",
+            r#"
+import "graphics" for Camera
+var created = 0
+var update = Fn.new { |dt|
+  Camera.new()
+  created = created + 1
+}
+"#,
+        );
+        assert!(update(&scripting).is_err());
+        assert!(update(&scripting).is_err());
+        assert_eq!(number(&scripting, "created"), Some(0.0));
+    }
+
+    #[test]
+    fn math_module() {
+        let scripting = load("math_test", include_str!("wren/math_test.wren"));
+        scripting.call(scripting.variable("run")).unwrap();
+    }
+
+    #[test]
+    fn failing_behaviour_stops_alone() {
+        let scripting = load(
+            "failing_behaviour",
+            r#"
+import "behaviour" for Behaviour, Behaviours
+var good = 0
+var bad = 0
+class Thing {
+  construct new(id) { _id = id }
+  id { _id }
+}
+class Good is Behaviour {
+  construct new(entity) { super(entity) }
+  update(dt) { good = good + 1 }
+}
+class Bad is Behaviour {
+  construct new(entity) { super(entity) }
+  update(dt) {
+    bad = bad + 1
+    Fiber.abort("bad behaviour")
+  }
+}
+var start = Fn.new {
+  Behaviours.attach(Thing.new(0), Bad)
+  Behaviours.attach(Thing.new(1), Good)
+}
+var update = Fn.new { |dt| }
+"#,
+        );
+        scripting.call(scripting.start_fn()).unwrap();
+        for _ in 0..3 {
+            assert!(update(&scripting).is_ok());
+        }
+        assert_eq!(number(&scripting, "good"), Some(3.0));
+        assert_eq!(number(&scripting, "bad"), Some(1.0));
+        // the failed one is removed
+        assert_eq!(scripting.behaviours.borrow().len(), 1);
+    }
+}
@@ -0,0 +1,65 @@
+use crate::vm::error::abort_init;
+use lazy_static::lazy_static;
+use lucien_core::scheduler::Frame;
+use lucien_core::time::{Clock, Time};
+use ruwren::{Class, VM};
+use std::sync::Mutex;
+
+lazy_static! {
+    // timing of current frame, ticked before script update
+    static ref TIME: Mutex<Time> = Mutex::new(Time::default());
+}
+
+// reset time, e.g. when a project is loaded
+pub fn reset_time(fixed_step: f64) {
+    *TIME.lock().unwrap() = Time::new(fixed_step);
+}
+
+// fixed steps of the frame decided by the loop scheduler
+pub fn schedule_time(frame: &Frame) {
+    TIME.lock().unwrap().schedule(frame.updates, frame.alpha);
+}
+
+// start a new frame, returns delta time in seconds
+pub fn tick_time(clock: &dyn Clock) -> f64 {
+    let mut time = TIME.lock().unwrap();
+    time.tick(clock);
+    time.delta
+}
+
+pub struct WrenTime;
+
+impl Class for WrenTime {
+    fn initialize(vm: &VM) -> Self {
+        abort_init(vm, "Time", WrenTime)
+    }
+}
+
+// static methods for wren, times are in seconds
+impl WrenTime {
+    pub fn elapsed(vm: &VM) {
+        vm.set_slot_double(0, TIME.lock().unwrap().elapsed);
+    }
+
+    pub fn delta(vm: &VM) {
+        vm.set_slot_double(0, TIME.lock().unwrap().delta);
+    }
+
+    pub fn frame(vm: &VM) {
+        vm.set_slot_double(0, TIME.lock().unwrap().frame as f64);
+    }
+
+    pub fn fixed_step(vm: &VM) {
+        vm.set_slot_double(0, TIME.lock().unwrap().fixed_step);
+    }
+
+    // returns true while there's a fixed step to run, e.g.
+    // while (Time.fixed_update()) { ... }
+    pub fn fixed_update(vm: &VM) {
+        vm.set_slot_bool(0, TIME.lock().unwrap().fixed_update());
+    }
+
+    pub fn alpha(vm: &VM) {
+        vm.set_slot_double(0, TIME.lock().unwrap().alpha());
+    }
+}
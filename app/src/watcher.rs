@@ -0,0 +1,48 @@
+use crate::message::Message;
+
+use anyhow::{Context, Result};
+use iced_winit::winit::event_loop::EventLoopProxy;
+use notify::{DebouncedEvent, RecursiveMode, Watcher};
+use slog::{debug, error};
+use std::path::PathBuf;
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+use lucien_core::logger::logger;
+
+// Watch files under project root, a `LoadProjectChange` message
+// with the path is sent to the main loop when a file is saved.
+pub fn watch_project(root: PathBuf, proxy: EventLoopProxy<Message>) -> Result<()> {
+    let (tx, rx) = channel();
+    // editors save a file in several writes, wait until they settle
+    let mut watcher =
+        notify::watcher(tx, Duration::from_millis(200)).context("Failed to create file watcher")?;
+    watcher
+        .watch(&root, RecursiveMode::Recursive)
+        .with_context(|| format!("Failed to watch {:?}", &root))?;
+
+    std::thread::spawn(move || {
+        // files are watched until the watcher is dropped
+        let _watcher = watcher;
+        for event in rx {
+            let path = match event {
+                DebouncedEvent::Create(path)
+                | DebouncedEvent::Write(path)
+                | DebouncedEvent::Rename(_, path) => path,
+                DebouncedEvent::Error(e, path) => {
+                    error!(logger(), "file watcher error on {:?}: {}", path, e);
+                    continue;
+                }
+                _ => continue,
+            };
+            debug!(logger(), "file changed: {:?}", path);
+            let msg = Message::LoadProjectChange(path.to_string_lossy().to_string());
+            // event loop is closed
+            if proxy.send_event(msg).is_err() {
+                break;
+            }
+        }
+    });
+
+    Ok(())
+}
@@ -22,6 +22,17 @@ impl UserInterface {
         }
     }
 
+    // set background from a linear color, e.g. the scene clear color
+    pub fn with_background_color(mut self, color: wgpu::Color) -> Self {
+        self.background_color = Color::from_rgba(
+            linear_to_srgb(color.r as f32),
+            linear_to_srgb(color.g as f32),
+            linear_to_srgb(color.b as f32),
+            color.a as f32,
+        );
+        self
+    }
+
     pub fn background_color(&self) -> wgpu::Color {
         let [r, g, b, a] = self.background_color.into_linear();
         wgpu::Color {
@@ -33,6 +44,15 @@ impl UserInterface {
     }
 }
 
+// inverse of iced `Color::into_linear`
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
 impl Program for UserInterface {
     type Renderer = Renderer;
     type Message = Message;
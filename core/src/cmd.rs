@@ -1,4 +1,4 @@
-use clap::{App, Arg};
+use clap::{App, Arg, SubCommand};
 
 pub struct Builder;
 
@@ -18,5 +18,50 @@ impl Builder {
                     .multiple(true)
                     .help("Sets the level of verbosity"),
             )
+            .subcommand(
+                SubCommand::with_name("render")
+                    .about("Renders frames offscreen without a window, saves them as png")
+                    .arg(
+                        Arg::with_name("project")
+                            .help("Sets the project root")
+                            .required(false)
+                            .default_value("."),
+                    )
+                    .arg(
+                        Arg::with_name("frames")
+                            .long("frames")
+                            .takes_value(true)
+                            .default_value("1")
+                            .help("Sets the number of frames to render"),
+                    )
+                    .arg(
+                        Arg::with_name("out")
+                            .long("out")
+                            .takes_value(true)
+                            .default_value("frames")
+                            .help("Sets the directory to write frames to"),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("pack")
+                    .about("Packs a project into one file, which can be run as a project")
+                    .arg(
+                        Arg::with_name("project")
+                            .help("Sets the project root")
+                            .required(false)
+                            .default_value("."),
+                    )
+                    .arg(
+                        Arg::with_name("out")
+                            .long("out")
+                            .takes_value(true)
+                            .help("Sets the pack file, defaults to <project directory name>.lpak"),
+                    )
+                    .arg(
+                        Arg::with_name("hash")
+                            .long("hash")
+                            .help("Stores hashes of files, checked when they're loaded"),
+                    ),
+            )
     }
 }
@@ -0,0 +1,449 @@
+use anyhow::{anyhow, Result};
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+
+// An entity is an index with a generation, so a handle to a despawned
+// entity doesn't refer to a new entity that reuses the index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+// Any plain data can be a component
+pub trait Component: Any + Send {}
+impl<T: Any + Send> Component for T {}
+
+// Components of one type, indexed by entity index
+#[derive(Debug)]
+pub struct Storage<T> {
+    components: Vec<Option<(Entity, T)>>,
+}
+
+// Storage with its component type erased, so a world can hold any
+trait AnyStorage: Send {
+    fn remove_entity(&mut self, entity: Entity);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+// Entities and their components
+#[derive(Default)]
+pub struct World {
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free: Vec<u32>,
+    storages: HashMap<TypeId, Box<dyn AnyStorage>>,
+}
+
+pub type System<T> = Box<dyn FnMut(&mut T) + Send>;
+
+// Systems run in order on every update, e.g. on a `World` or on
+// something that holds one. Systems are named, so a system can be
+// placed before or after another.
+pub struct Schedule<T> {
+    systems: Vec<(String, System<T>)>,
+}
+
+impl Entity {
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    // pack into a number, e.g. to hand it to scripts;
+    // integers below 2^53 survive a trip through f64
+    pub fn to_bits(self) -> u64 {
+        (self.generation as u64) << 32 | self.index as u64
+    }
+
+    pub fn from_bits(bits: u64) -> Self {
+        Self {
+            index: bits as u32,
+            generation: (bits >> 32) as u32,
+        }
+    }
+}
+
+impl<T> Storage<T> {
+    pub fn new() -> Self {
+        Self { components: vec![] }
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        match self.components.get(entity.index as usize) {
+            Some(Some((e, component))) if *e == entity => Some(component),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        match self.components.get_mut(entity.index as usize) {
+            Some(Some((e, component))) if *e == entity => Some(component),
+            _ => None,
+        }
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.get(entity).is_some()
+    }
+
+    // returns the component replaced
+    pub fn insert(&mut self, entity: Entity, component: T) -> Option<T> {
+        let index = entity.index as usize;
+        if index >= self.components.len() {
+            self.components.resize_with(index + 1, || None);
+        }
+        match self.components[index].replace((entity, component)) {
+            Some((e, old)) if e == entity => Some(old),
+            _ => None,
+        }
+    }
+
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        if !self.contains(entity) {
+            return None;
+        }
+        self.components[entity.index as usize]
+            .take()
+            .map(|(_, component)| component)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.components
+            .iter()
+            .filter_map(|slot| slot.as_ref().map(|(e, component)| (*e, component)))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        self.components
+            .iter_mut()
+            .filter_map(|slot| slot.as_mut().map(|(e, component)| (*e, component)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+}
+
+impl<T> Default for Storage<T> {
+    fn default() -> Self {
+        Storage::new()
+    }
+}
+
+impl<T: Component> AnyStorage for Storage<T> {
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(entity);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn spawn(&mut self) -> Entity {
+        match self.free.pop() {
+            Some(index) => {
+                self.alive[index as usize] = true;
+                Entity {
+                    index,
+                    generation: self.generations[index as usize],
+                }
+            }
+            None => {
+                self.generations.push(0);
+                self.alive.push(true);
+                Entity {
+                    index: self.generations.len() as u32 - 1,
+                    generation: 0,
+                }
+            }
+        }
+    }
+
+    // remove an entity and all its components,
+    // returns false if it's despawned already
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+        for storage in self.storages.values_mut() {
+            storage.remove_entity(entity);
+        }
+        let index = entity.index as usize;
+        self.alive[index] = false;
+        self.generations[index] += 1;
+        self.free.push(entity.index);
+        true
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        let index = entity.index as usize;
+        index < self.alive.len()
+            && self.alive[index]
+            && self.generations[index] == entity.generation
+    }
+
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.alive
+            .iter()
+            .enumerate()
+            .filter(|(_, alive)| **alive)
+            .map(move |(index, _)| Entity {
+                index: index as u32,
+                generation: self.generations[index],
+            })
+    }
+
+    // add or replace a component of an entity,
+    // returns false if the entity is despawned
+    pub fn insert<T: Component>(&mut self, entity: Entity, component: T) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+        self.storage_mut::<T>().insert(entity, component);
+        true
+    }
+
+    pub fn remove<T: Component>(&mut self, entity: Entity) -> Option<T> {
+        self.storage_mut::<T>().remove(entity)
+    }
+
+    pub fn get<T: Component>(&self, entity: Entity) -> Option<&T> {
+        self.storage::<T>()?.get(entity)
+    }
+
+    pub fn get_mut<T: Component>(&mut self, entity: Entity) -> Option<&mut T> {
+        self.storage_mut::<T>().get_mut(entity)
+    }
+
+    pub fn has<T: Component>(&self, entity: Entity) -> bool {
+        self.get::<T>(entity).is_some()
+    }
+
+    // None if no component of the type was ever inserted
+    pub fn storage<T: Component>(&self) -> Option<&Storage<T>> {
+        self.storages
+            .get(&TypeId::of::<T>())
+            .and_then(|storage| storage.as_any().downcast_ref())
+    }
+
+    pub fn storage_mut<T: Component>(&mut self) -> &mut Storage<T> {
+        self.storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(Storage::<T>::new()))
+            .as_any_mut()
+            .downcast_mut()
+            .unwrap()
+    }
+
+    // entities with a component of type A
+    pub fn query<A: Component>(&self) -> impl Iterator<Item = (Entity, &A)> {
+        self.storage::<A>().into_iter().flat_map(|a| a.iter())
+    }
+
+    pub fn query_mut<A: Component>(&mut self) -> impl Iterator<Item = (Entity, &mut A)> {
+        self.storage_mut::<A>().iter_mut()
+    }
+
+    // entities with components of both type A and B
+    pub fn query2<A: Component, B: Component>(&self) -> impl Iterator<Item = (Entity, &A, &B)> {
+        let b = self.storage::<B>();
+        self.query::<A>()
+            .filter_map(move |(e, a)| b.and_then(|b| b.get(e)).map(|b| (e, a, b)))
+    }
+}
+
+impl fmt::Debug for World {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("World")
+            .field("entities", &self.entities().count())
+            .field("storages", &self.storages.len())
+            .finish()
+    }
+}
+
+impl<T> Schedule<T> {
+    pub fn new() -> Self {
+        Self { systems: vec![] }
+    }
+
+    // run after every system added before
+    pub fn add<F>(&mut self, name: &str, system: F)
+    where
+        F: FnMut(&mut T) + Send + 'static,
+    {
+        self.systems.push((name.to_string(), Box::new(system)));
+    }
+
+    pub fn add_before<F>(&mut self, name: &str, before: &str, system: F) -> Result<()>
+    where
+        F: FnMut(&mut T) + Send + 'static,
+    {
+        let index = self.position(before)?;
+        self.systems
+            .insert(index, (name.to_string(), Box::new(system)));
+        Ok(())
+    }
+
+    pub fn add_after<F>(&mut self, name: &str, after: &str, system: F) -> Result<()>
+    where
+        F: FnMut(&mut T) + Send + 'static,
+    {
+        let index = self.position(after)?;
+        self.systems
+            .insert(index + 1, (name.to_string(), Box::new(system)));
+        Ok(())
+    }
+
+    // returns false if there's no such system
+    pub fn remove(&mut self, name: &str) -> bool {
+        let len = self.systems.len();
+        self.systems.retain(|(n, _)| n != name);
+        self.systems.len() != len
+    }
+
+    fn position(&self, name: &str) -> Result<usize> {
+        self.systems
+            .iter()
+            .position(|(n, _)| n == name)
+            .ok_or_else(|| anyhow!("no system named {}", name))
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.systems.iter().map(|(name, _)| name.as_str())
+    }
+
+    pub fn run(&mut self, target: &mut T) {
+        for (_, system) in self.systems.iter_mut() {
+            system(target);
+        }
+    }
+}
+
+impl<T> Default for Schedule<T> {
+    fn default() -> Self {
+        Schedule::new()
+    }
+}
+
+impl<T> fmt::Debug for Schedule<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Position(f32);
+    #[derive(Debug, PartialEq)]
+    struct Velocity(f32);
+
+    #[test]
+    fn respawn_bumps_generation() {
+        let mut world = World::new();
+        let old = world.spawn();
+        world.insert(old, Position(1.0));
+        assert!(world.despawn(old));
+        assert!(!world.despawn(old));
+
+        let new = world.spawn();
+        assert_eq!(new.index(), old.index());
+        assert_eq!(new.generation(), old.generation() + 1);
+        assert!(world.is_alive(new));
+        // the stale handle doesn't see the new entity
+        assert!(!world.is_alive(old));
+        assert!(!world.insert(old, Position(2.0)));
+        assert!(world.get::<Position>(new).is_none());
+        world.insert(new, Position(3.0));
+        assert!(world.get::<Position>(old).is_none());
+        assert_eq!(world.get::<Position>(new), Some(&Position(3.0)));
+        assert_eq!(world.entities().collect::<Vec<_>>(), vec![new]);
+    }
+
+    #[test]
+    fn entity_bits_round_trip() {
+        let mut world = World::new();
+        let first = world.spawn();
+        world.despawn(first);
+        let entity = world.spawn();
+        let bits = entity.to_bits();
+        assert_eq!(Entity::from_bits(bits), entity);
+        assert_eq!(bits, 1 << 32);
+        // bits go through f64 in scripts
+        assert_eq!(Entity::from_bits(bits as f64 as u64), entity);
+    }
+
+    #[test]
+    fn query2_skips_entities_missing_a_component() {
+        let mut world = World::new();
+        let entities: Vec<Entity> = (0..7).map(|_| world.spawn()).collect();
+        for (i, entity) in entities.iter().enumerate() {
+            if i % 2 == 0 {
+                world.insert(*entity, Position(i as f32));
+            }
+            if i % 3 == 0 {
+                world.insert(*entity, Velocity(i as f32));
+            }
+        }
+        let found: Vec<(Entity, f32, f32)> = world
+            .query2::<Position, Velocity>()
+            .map(|(e, p, v)| (e, p.0, v.0))
+            .collect();
+        assert_eq!(
+            found,
+            vec![(entities[0], 0.0, 0.0), (entities[6], 6.0, 6.0)]
+        );
+        // no storage of a type yet
+        assert_eq!(world.query2::<Position, String>().count(), 0);
+    }
+
+    #[test]
+    fn systems_run_in_order() {
+        let mut schedule: Schedule<Vec<&'static str>> = Schedule::new();
+        schedule.add("physics", |log| log.push("physics"));
+        schedule.add("render", |log| log.push("render"));
+        schedule
+            .add_before("input", "physics", |log| log.push("input"))
+            .unwrap();
+        schedule
+            .add_after("collide", "physics", |log| log.push("collide"))
+            .unwrap();
+        assert!(schedule.add_after("audio", "missing", |_| {}).is_err());
+
+        let mut log = vec![];
+        schedule.run(&mut log);
+        assert_eq!(log, vec!["input", "physics", "collide", "render"]);
+        assert_eq!(
+            schedule.names().collect::<Vec<_>>(),
+            vec!["input", "physics", "collide", "render"]
+        );
+
+        assert!(schedule.remove("collide"));
+        assert!(!schedule.remove("collide"));
+        log.clear();
+        schedule.run(&mut log);
+        assert_eq!(log, vec!["input", "physics", "render"]);
+    }
+}
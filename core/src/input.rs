@@ -0,0 +1,241 @@
+use std::collections::{BTreeMap, HashSet};
+
+// Events from keyboard and mouse, independent of window library.
+// Buttons are named: keys by their lowercase name, e.g. `w`, `space`,
+// `left`, `escape`; mouse buttons are `mouse_left`, `mouse_right`,
+// `mouse_middle`, or `mouse_<n>` for others.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputEvent {
+    Pressed(String),
+    Released(String),
+    // cursor position in pixels
+    CursorMoved(f32, f32),
+    // scroll in lines
+    Scroll(f32, f32),
+    // window lost focus, nothing is held anymore
+    FocusLost,
+}
+
+// Named actions bound to buttons, e.g. `jump = ["space", "w"]`
+pub type ActionBindings = BTreeMap<String, Vec<String>>;
+
+// Input state of a frame. Events are handled as they arrive, `end_frame`
+// is called after the game updates, so `just_pressed` and `released`
+// last for one frame.
+#[derive(Debug, Default)]
+pub struct Input {
+    held: HashSet<String>,
+    just_pressed: HashSet<String>,
+    released: HashSet<String>,
+    cursor: Option<(f32, f32)>,
+    mouse_delta: (f32, f32),
+    scroll: (f32, f32),
+    actions: ActionBindings,
+}
+
+impl Input {
+    pub fn new(actions: ActionBindings) -> Self {
+        Self {
+            actions,
+            ..Default::default()
+        }
+    }
+
+    pub fn handle(&mut self, event: InputEvent) {
+        match event {
+            InputEvent::Pressed(button) => {
+                // key repeat doesn't press it again
+                if self.held.insert(button.clone()) {
+                    self.just_pressed.insert(button);
+                }
+            }
+            InputEvent::Released(button) => {
+                if self.held.remove(&button) {
+                    self.released.insert(button);
+                }
+            }
+            InputEvent::CursorMoved(x, y) => {
+                if let Some((last_x, last_y)) = self.cursor {
+                    self.mouse_delta.0 += x - last_x;
+                    self.mouse_delta.1 += y - last_y;
+                }
+                self.cursor = Some((x, y));
+            }
+            InputEvent::Scroll(x, y) => {
+                self.scroll.0 += x;
+                self.scroll.1 += y;
+            }
+            InputEvent::FocusLost => {
+                self.released.extend(self.held.drain());
+            }
+        }
+    }
+
+    // forget what happened this frame, held buttons stay held
+    pub fn end_frame(&mut self) {
+        self.just_pressed.clear();
+        self.released.clear();
+        self.mouse_delta = (0.0, 0.0);
+        self.scroll = (0.0, 0.0);
+    }
+
+    // button is held down
+    pub fn pressed(&self, button: &str) -> bool {
+        self.held.contains(button)
+    }
+
+    // button went down this frame
+    pub fn just_pressed(&self, button: &str) -> bool {
+        self.just_pressed.contains(button)
+    }
+
+    // button went up this frame
+    pub fn released(&self, button: &str) -> bool {
+        self.released.contains(button)
+    }
+
+    // any button of the action is held
+    pub fn action_pressed(&self, action: &str) -> bool {
+        self.buttons(action).any(|button| self.pressed(button))
+    }
+
+    // the action starts this frame, no other button of it was held
+    pub fn action_just_pressed(&self, action: &str) -> bool {
+        self.buttons(action).any(|button| self.just_pressed(button))
+            && self
+                .buttons(action)
+                .all(|button| !self.pressed(button) || self.just_pressed(button))
+    }
+
+    // the action stops this frame, its last held button went up
+    pub fn action_released(&self, action: &str) -> bool {
+        self.buttons(action).any(|button| self.released(button)) && !self.action_pressed(action)
+    }
+
+    fn buttons<'a>(&'a self, action: &str) -> impl Iterator<Item = &'a str> {
+        self.actions
+            .get(action)
+            .into_iter()
+            .flat_map(|buttons| buttons.iter().map(|button| button.as_str()))
+    }
+
+    pub fn bind(&mut self, action: &str, buttons: &[&str]) {
+        let buttons = buttons.iter().map(|button| button.to_string()).collect();
+        self.actions.insert(action.to_string(), buttons);
+    }
+
+    pub fn cursor(&self) -> (f32, f32) {
+        self.cursor.unwrap_or((0.0, 0.0))
+    }
+
+    // cursor movement this frame
+    pub fn mouse_delta(&self) -> (f32, f32) {
+        self.mouse_delta
+    }
+
+    // scroll this frame
+    pub fn scroll(&self) -> (f32, f32) {
+        self.scroll
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(input: &mut Input, button: &str) {
+        input.handle(InputEvent::Pressed(button.to_string()));
+    }
+
+    fn release(input: &mut Input, button: &str) {
+        input.handle(InputEvent::Released(button.to_string()));
+    }
+
+    #[test]
+    fn just_pressed_lasts_one_frame() {
+        let mut input = Input::default();
+        press(&mut input, "space");
+        assert!(input.pressed("space"));
+        assert!(input.just_pressed("space"));
+        input.end_frame();
+        // key repeat
+        press(&mut input, "space");
+        assert!(input.pressed("space"));
+        assert!(!input.just_pressed("space"));
+    }
+
+    #[test]
+    fn released_lasts_one_frame() {
+        let mut input = Input::default();
+        press(&mut input, "mouse_left");
+        input.end_frame();
+        release(&mut input, "mouse_left");
+        assert!(!input.pressed("mouse_left"));
+        assert!(input.released("mouse_left"));
+        input.end_frame();
+        assert!(!input.released("mouse_left"));
+        // releasing what isn't held does nothing
+        release(&mut input, "w");
+        assert!(!input.released("w"));
+    }
+
+    #[test]
+    fn press_and_release_in_one_frame() {
+        let mut input = Input::default();
+        press(&mut input, "w");
+        release(&mut input, "w");
+        assert!(!input.pressed("w"));
+        assert!(input.just_pressed("w"));
+        assert!(input.released("w"));
+    }
+
+    #[test]
+    fn mouse_delta_and_scroll() {
+        let mut input = Input::default();
+        // first position has no delta
+        input.handle(InputEvent::CursorMoved(10.0, 10.0));
+        input.handle(InputEvent::CursorMoved(15.0, 8.0));
+        input.handle(InputEvent::CursorMoved(20.0, 9.0));
+        input.handle(InputEvent::Scroll(0.0, 1.0));
+        input.handle(InputEvent::Scroll(0.0, 2.0));
+        assert_eq!(input.cursor(), (20.0, 9.0));
+        assert_eq!(input.mouse_delta(), (10.0, -1.0));
+        assert_eq!(input.scroll(), (0.0, 3.0));
+        input.end_frame();
+        assert_eq!(input.mouse_delta(), (0.0, 0.0));
+        assert_eq!(input.scroll(), (0.0, 0.0));
+        assert_eq!(input.cursor(), (20.0, 9.0));
+    }
+
+    #[test]
+    fn actions() {
+        let mut input = Input::default();
+        input.bind("jump", &["space", "w"]);
+        press(&mut input, "space");
+        assert!(input.action_pressed("jump"));
+        assert!(input.action_just_pressed("jump"));
+        input.end_frame();
+        // another button of a held action doesn't start it again
+        press(&mut input, "w");
+        assert!(!input.action_just_pressed("jump"));
+        release(&mut input, "space");
+        assert!(!input.action_released("jump"));
+        input.end_frame();
+        release(&mut input, "w");
+        assert!(input.action_released("jump"));
+        assert!(!input.action_pressed("jump"));
+        assert!(!input.action_pressed("unknown"));
+    }
+
+    #[test]
+    fn focus_lost_releases_everything() {
+        let mut input = Input::default();
+        press(&mut input, "a");
+        press(&mut input, "mouse_right");
+        input.end_frame();
+        input.handle(InputEvent::FocusLost);
+        assert!(!input.pressed("a"));
+        assert!(input.released("a"));
+        assert!(input.released("mouse_right"));
+    }
+}
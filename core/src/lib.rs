@@ -1,6 +1,10 @@
 pub mod cmd;
+pub mod ecs;
+pub mod input;
 pub mod logger;
 pub mod resources;
+pub mod scheduler;
+pub mod time;
 
 pub type Logger = slog::Logger;
 pub type ArgFlags = clap::ArgMatches<'static>;
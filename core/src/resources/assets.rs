@@ -0,0 +1,620 @@
+use anyhow::{anyhow, Context, Result};
+use image::RgbaImage;
+use lazy_static::lazy_static;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Deref;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use tobj::{Material, Model};
+
+use super::loader::{loader, Gltf, ResourceLoader};
+use super::pool::LoadPool;
+use crate::logger::logger;
+use slog::{debug, warn};
+
+lazy_static! {
+    static ref ASSETS: AssetServer = AssetServer::new();
+    // started on first background load
+    static ref POOL: LoadPool = LoadPool::new(LOAD_WORKERS);
+}
+
+// Assets shared by render and scripts
+pub fn assets() -> &'static AssetServer {
+    &ASSETS
+}
+
+// A resource the asset server can load from a path
+pub trait Asset: Send + Sync + Sized + 'static {
+    fn load(loader: &dyn ResourceLoader, path: &str) -> Result<Self>;
+}
+
+// Models of .obj, or a glTF document, by extension of the path
+pub enum MeshData {
+    Obj(Vec<Model>, Vec<Material>),
+    Gltf(Box<Gltf>),
+}
+
+impl Asset for MeshData {
+    fn load(loader: &dyn ResourceLoader, path: &str) -> Result<Self> {
+        let ext = Path::new(path).extension().and_then(|ext| ext.to_str());
+        match ext {
+            Some("obj") => {
+                let (models, materials) = loader.load_obj(path)?;
+                Ok(MeshData::Obj(models, materials))
+            }
+            Some("gltf") | Some("glb") => Ok(MeshData::Gltf(Box::new(loader.load_gltf(path)?))),
+            _ => Err(anyhow!("unsupported mesh format: {}", path)),
+        }
+    }
+}
+
+pub struct TextureData(pub RgbaImage);
+
+impl Asset for TextureData {
+    fn load(loader: &dyn ResourceLoader, path: &str) -> Result<Self> {
+        Ok(TextureData(loader.load_rgba(path)?))
+    }
+}
+
+// Source of a glsl shader or include
+pub struct ShaderSource(pub String);
+
+impl Asset for ShaderSource {
+    fn load(loader: &dyn ResourceLoader, path: &str) -> Result<Self> {
+        Ok(ShaderSource(loader.load_text(path)?))
+    }
+}
+
+// Source of a wren module
+pub struct ScriptSource(pub String);
+
+impl Asset for ScriptSource {
+    fn load(loader: &dyn ResourceLoader, path: &str) -> Result<Self> {
+        Ok(ScriptSource(loader.load_text(path)?))
+    }
+}
+
+// A loaded asset. Clones share the asset, which is unloaded
+// when the last handle is dropped.
+pub struct Handle<T> {
+    path: Arc<str>,
+    asset: Arc<T>,
+}
+
+impl<T> Handle<T> {
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    // number of handles to the asset
+    pub fn ref_count(&self) -> usize {
+        Arc::strong_count(&self.asset)
+    }
+
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.asset, &other.asset)
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            path: self.path.clone(),
+            asset: self.asset.clone(),
+        }
+    }
+}
+
+impl<T> Deref for Handle<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.asset
+    }
+}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Handle")
+            .field("path", &self.path)
+            .field("refs", &self.ref_count())
+            .finish()
+    }
+}
+
+type Key = (TypeId, String);
+type AnyAsset = Arc<dyn Any + Send + Sync>;
+
+// workers of the background loads
+const LOAD_WORKERS: usize = 4;
+
+// State of a background load
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadState {
+    Pending,
+    Loaded,
+    Failed(String),
+}
+
+#[derive(Default)]
+struct Loads {
+    // pending and failed loads; loaded ones are in assets
+    states: HashMap<Key, LoadState>,
+    // assets loaded in background, kept until they're taken
+    retained: HashMap<Key, AnyAsset>,
+}
+
+// Map paths to loaded assets. An asset is loaded once while it has
+// handles; the server keeps no handle itself, so unused assets are
+// unloaded and loaded again on next request.
+// Assets loaded in background are kept until the first `load` takes
+// them, e.g. render thread uploads meshes decoded by loader threads.
+#[derive(Default)]
+pub struct AssetServer {
+    assets: Mutex<HashMap<Key, Weak<dyn Any + Send + Sync>>>,
+    loads: Mutex<Loads>,
+    // notified when a background load is done
+    done: Condvar,
+}
+
+impl AssetServer {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    // load with the resource loader of the project
+    pub fn load<T: Asset>(&self, path: &str) -> Result<Handle<T>> {
+        self.load_from(loader()?, path)
+    }
+
+    // load with another resource loader, e.g. of a script root
+    pub fn load_from<T: Asset>(
+        &self, loader: &dyn ResourceLoader, path: &str,
+    ) -> Result<Handle<T>> {
+        self.load_with(path, || T::load(loader, path))
+    }
+
+    // load with a function, for assets that are not files,
+    // e.g. builtin textures; the path names the asset.
+    // Waits if the asset is being loaded in background.
+    pub fn load_with<T, F>(&self, path: &str, load: F) -> Result<Handle<T>>
+    where
+        T: Send + Sync + 'static,
+        F: FnOnce() -> Result<T>,
+    {
+        if let Some(LoadState::Failed(e)) = self.wait::<T>(path) {
+            return Err(anyhow!("{}", e).context(format!("Failed to load asset {}", path)));
+        }
+        if let Some(handle) = self.get(path) {
+            self.release::<T>(path);
+            return Ok(handle);
+        }
+        // not locked while loading, other assets can be loaded meanwhile
+        let asset = Arc::new(load().with_context(|| format!("Failed to load asset {}", path))?);
+        debug!(logger(), "asset loaded: {}", path);
+        Ok(Handle {
+            path: path.into(),
+            asset: self.insert(path, asset),
+        })
+    }
+
+    // load in background with the resource loader of the project,
+    // the state of the load is queried with `state::<T>`
+    pub fn load_async<T: Asset>(&'static self, path: &str) {
+        let name = path.to_string();
+        self.load_async_with(path, move || T::load(loader()?, &name));
+    }
+
+    // load in background with a function, like `load_with`
+    pub fn load_async_with<T, F>(&'static self, path: &str, load: F)
+    where
+        T: Send + Sync + 'static,
+        F: FnOnce() -> Result<T> + Send + 'static,
+    {
+        let key = (TypeId::of::<T>(), path.to_string());
+        {
+            let mut loads = self.loads.lock().unwrap();
+            if loads.states.get(&key) == Some(&LoadState::Pending)
+                || loads.retained.contains_key(&key)
+            {
+                return;
+            }
+            // a loaded asset is kept until taken, like loaded in background
+            if let Some(handle) = self.get::<T>(path) {
+                let asset: AnyAsset = handle.asset;
+                loads.states.remove(&key);
+                loads.retained.insert(key, asset);
+                return;
+            }
+            loads.states.insert(key.clone(), LoadState::Pending);
+        }
+
+        let path = path.to_string();
+        POOL.spawn(move || {
+            let res = panic::catch_unwind(AssertUnwindSafe(load))
+                .unwrap_or_else(|_| Err(anyhow!("loader thread panicked")));
+            let mut loads = self.loads.lock().unwrap();
+            match res {
+                Ok(asset) => {
+                    debug!(logger(), "asset loaded in background: {}", path);
+                    let asset: AnyAsset = self.insert::<T>(&path, Arc::new(asset));
+                    loads.states.remove(&key);
+                    loads.retained.insert(key, asset);
+                }
+                Err(e) => {
+                    warn!(logger(), "failed to load asset {}: {:#}", path, e);
+                    loads
+                        .states
+                        .insert(key, LoadState::Failed(format!("{:#}", e)));
+                }
+            }
+            self.done.notify_all();
+        });
+    }
+
+    // state of an asset, None if it's not loaded
+    pub fn state<T: Send + Sync + 'static>(&self, path: &str) -> Option<LoadState> {
+        let loads = self.loads.lock().unwrap();
+        if let Some(state) = loads.states.get(&(TypeId::of::<T>(), path.to_string())) {
+            return Some(state.clone());
+        }
+        self.get::<T>(path).map(|_| LoadState::Loaded)
+    }
+
+    // state of the assets of a path, whatever their type:
+    // pending if any load is pending, then failed, then loaded
+    pub fn state_all(&self, path: &str) -> Option<LoadState> {
+        let loads = self.loads.lock().unwrap();
+        let states: Vec<&LoadState> = loads
+            .states
+            .iter()
+            .filter(|((_, p), _)| p == path)
+            .map(|(_, state)| state)
+            .collect();
+        let pending = states.iter().find(|state| ***state == LoadState::Pending);
+        if let Some(state) = pending.or_else(|| states.first()) {
+            return Some((*state).clone());
+        }
+        let assets = self.assets.lock().unwrap();
+        let loaded = assets
+            .iter()
+            .any(|((_, p), asset)| p == path && asset.strong_count() > 0);
+        if loaded {
+            Some(LoadState::Loaded)
+        } else {
+            None
+        }
+    }
+
+    // number of loads in background
+    pub fn pending(&self) -> usize {
+        let loads = self.loads.lock().unwrap();
+        loads
+            .states
+            .values()
+            .filter(|state| **state == LoadState::Pending)
+            .count()
+    }
+
+    // stop keeping an asset loaded in background,
+    // it's unloaded if there's no handle to it
+    pub fn release<T: 'static>(&self, path: &str) {
+        let mut loads = self.loads.lock().unwrap();
+        let key = (TypeId::of::<T>(), path.to_string());
+        loads.retained.remove(&key);
+        if let Some(LoadState::Failed(_)) = loads.states.get(&key) {
+            loads.states.remove(&key);
+        }
+    }
+
+    // release every asset of the path, whatever its type
+    pub fn release_all(&self, path: &str) {
+        let mut loads = self.loads.lock().unwrap();
+        loads.retained.retain(|(_, p), _| p != path);
+        loads
+            .states
+            .retain(|(_, p), state| p != path || *state == LoadState::Pending);
+    }
+
+    // a loaded asset, without loading it
+    pub fn get<T: Send + Sync + 'static>(&self, path: &str) -> Option<Handle<T>> {
+        let assets = self.assets.lock().unwrap();
+        let asset = assets
+            .get(&(TypeId::of::<T>(), path.to_string()))?
+            .upgrade()?
+            .downcast::<T>()
+            .ok()?;
+        Some(Handle {
+            path: path.into(),
+            asset,
+        })
+    }
+
+    // forget assets loaded from a changed file, next load reads it again;
+    // `changed` can be absolute, it matches assets it ends with
+    pub fn invalidate(&self, changed: &Path) {
+        let mut loads = self.loads.lock().unwrap();
+        loads
+            .retained
+            .retain(|(_, path), _| !changed.ends_with(path));
+        loads
+            .states
+            .retain(|(_, path), state| *state == LoadState::Pending || !changed.ends_with(path));
+        let mut assets = self.assets.lock().unwrap();
+        assets.retain(|(_, path), asset| {
+            let stale = changed.ends_with(path);
+            if stale && asset.strong_count() > 0 {
+                debug!(logger(), "asset invalidated: {}", path);
+            }
+            !stale && asset.strong_count() > 0
+        });
+    }
+
+    // number of loaded assets
+    pub fn len(&self) -> usize {
+        let assets = self.assets.lock().unwrap();
+        assets
+            .values()
+            .filter(|asset| asset.strong_count() > 0)
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // add a loaded asset; if it was loaded by another thread
+    // meanwhile, the first one is kept and returned
+    fn insert<T: Send + Sync + 'static>(&self, path: &str, asset: Arc<T>) -> Arc<T> {
+        let mut assets = self.assets.lock().unwrap();
+        assets.retain(|_, asset| asset.strong_count() > 0);
+        let key = (TypeId::of::<T>(), path.to_string());
+        if let Some(loaded) = assets.get(&key).and_then(|weak| weak.upgrade()) {
+            if let Ok(loaded) = loaded.downcast::<T>() {
+                return loaded;
+            }
+        }
+        let any: AnyAsset = asset.clone();
+        assets.insert(key, Arc::downgrade(&any));
+        asset
+    }
+
+    // wait until a background load of the asset is done,
+    // returns its state if it failed
+    fn wait<T: 'static>(&self, path: &str) -> Option<LoadState> {
+        let key = (TypeId::of::<T>(), path.to_string());
+        let mut loads = self.loads.lock().unwrap();
+        while loads.states.get(&key) == Some(&LoadState::Pending) {
+            loads = self.done.wait(loads).unwrap();
+        }
+        loads.states.get(&key).cloned()
+    }
+}
+
+impl fmt::Debug for AssetServer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AssetServer")
+            .field("assets", &self.len())
+            .field("pending", &self.pending())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::MemoryLoader;
+    use std::cell::Cell;
+    use std::sync::mpsc::channel;
+
+    fn scripts() -> MemoryLoader {
+        MemoryLoader::new()
+            .file("main.wren", "var x = 1")
+            .file("other.wren", "var y = 2")
+    }
+
+    // background loads need a server that lives as long as loader threads
+    fn server() -> &'static AssetServer {
+        Box::leak(Box::new(AssetServer::new()))
+    }
+
+    #[test]
+    fn same_path_is_loaded_once() {
+        let server = AssetServer::new();
+        let loader = scripts();
+        let a = server
+            .load_from::<ScriptSource>(&loader, "main.wren")
+            .unwrap();
+        let b = server
+            .load_from::<ScriptSource>(&loader, "main.wren")
+            .unwrap();
+        assert!(a.ptr_eq(&b));
+        assert_eq!(a.0, "var x = 1");
+        assert_eq!(a.path(), "main.wren");
+
+        let other = server
+            .load_from::<ScriptSource>(&loader, "other.wren")
+            .unwrap();
+        assert!(!a.ptr_eq(&other));
+        // a path loaded as another type is another asset
+        let shader = server
+            .load_from::<ShaderSource>(&loader, "main.wren")
+            .unwrap();
+        assert_eq!(shader.0, "var x = 1");
+        assert_eq!(server.len(), 3);
+    }
+
+    #[test]
+    fn handles_count_references() {
+        let server = AssetServer::new();
+        let loads = Cell::new(0);
+        let load = || {
+            loads.set(loads.get() + 1);
+            Ok(42)
+        };
+        let a = server.load_with("answer", load).unwrap();
+        assert_eq!(a.ref_count(), 1);
+        let b = server.load_with("answer", load).unwrap();
+        let c = b.clone();
+        // the server holds no handle itself
+        assert_eq!(a.ref_count(), 3);
+        assert_eq!(*c, 42);
+        drop(b);
+        assert_eq!(a.ref_count(), 2);
+        assert_eq!(loads.get(), 1);
+    }
+
+    #[test]
+    fn asset_is_unloaded_with_last_handle() {
+        let server = AssetServer::new();
+        let loads = Cell::new(0);
+        let load = || {
+            loads.set(loads.get() + 1);
+            Ok(String::from("asset"))
+        };
+        let a = server.load_with("asset", load).unwrap();
+        let b = a.clone();
+        drop(a);
+        assert!(server.get::<String>("asset").is_some());
+        drop(b);
+        assert!(server.get::<String>("asset").is_none());
+        assert!(server.is_empty());
+        assert_eq!(server.state::<String>("asset"), None);
+
+        // it's loaded again on next request
+        let _a = server.load_with("asset", load).unwrap();
+        assert_eq!(loads.get(), 2);
+        assert_eq!(server.len(), 1);
+    }
+
+    #[test]
+    fn load_waits_for_background_load() {
+        let server = server();
+        let (tx, rx) = channel();
+        server.load_async_with("slow", move || {
+            rx.recv().unwrap();
+            Ok(7)
+        });
+        assert_eq!(server.state::<i32>("slow"), Some(LoadState::Pending));
+        assert_eq!(server.pending(), 1);
+        // a pending load isn't started twice
+        server.load_async_with("slow", || Ok(0));
+
+        tx.send(()).unwrap();
+        let handle: Handle<i32> = server
+            .load_with("slow", || Err(anyhow!("not loaded in background")))
+            .unwrap();
+        assert_eq!(*handle, 7);
+        assert_eq!(server.pending(), 0);
+        assert_eq!(server.state::<i32>("slow"), Some(LoadState::Loaded));
+        // taken by the load, nothing keeps it anymore
+        drop(handle);
+        assert_eq!(server.state::<i32>("slow"), None);
+    }
+
+    #[test]
+    fn background_load_is_kept_until_taken() {
+        let server = server();
+        server.load_async_with("kept", || Ok(1));
+        assert_eq!(server.wait::<i32>("kept"), None);
+        assert_eq!(server.state::<i32>("kept"), Some(LoadState::Loaded));
+        assert_eq!(server.get::<i32>("kept").map(|handle| *handle), Some(1));
+        server.release::<i32>("kept");
+        assert_eq!(server.state::<i32>("kept"), None);
+    }
+
+    #[test]
+    fn failed_load_is_retried() {
+        let server = server();
+        server.load_async_with::<i32, _>("bad", || Err(anyhow!("broken")));
+        match server.wait::<i32>("bad") {
+            Some(LoadState::Failed(e)) => assert!(e.contains("broken"), "{}", e),
+            state => panic!("unexpected state {:?}", state),
+        }
+        // the failure is reported until released
+        let error = server.load_with("bad", || Ok(1)).unwrap_err();
+        assert!(format!("{:#}", error).contains("broken"), "{:#}", error);
+        assert!(matches!(
+            server.state::<i32>("bad"),
+            Some(LoadState::Failed(_))
+        ));
+
+        // loading in background again retries
+        server.load_async_with("bad", || Ok(2));
+        assert_eq!(server.wait::<i32>("bad"), None);
+        assert_eq!(*server.load_with("bad", || Ok(3)).unwrap(), 2);
+
+        server.load_async_with::<i32, _>("worse", || Err(anyhow!("broken")));
+        server.wait::<i32>("worse");
+        server.release::<i32>("worse");
+        assert_eq!(server.state::<i32>("worse"), None);
+        assert_eq!(*server.load_with("worse", || Ok(4)).unwrap(), 4);
+    }
+
+    #[test]
+    fn types_of_a_path_load_separately() {
+        let server = server();
+        let (tx, rx) = channel();
+        server.load_async_with::<String, _>("data.bin", move || {
+            rx.recv().unwrap();
+            Err(anyhow!("not text"))
+        });
+        server.load_async_with("data.bin", || Ok(5_i32));
+        assert_eq!(server.wait::<i32>("data.bin"), None);
+        // the pending load of another type doesn't block this one
+        assert_eq!(*server.load_with("data.bin", || Ok(0_i32)).unwrap(), 5);
+        assert_eq!(server.state::<String>("data.bin"), Some(LoadState::Pending));
+        assert_eq!(server.state_all("data.bin"), Some(LoadState::Pending));
+
+        tx.send(()).unwrap();
+        assert!(matches!(
+            server.wait::<String>("data.bin"),
+            Some(LoadState::Failed(_))
+        ));
+        // the failure is only of its type
+        let handle = server.load_with("data.bin", || Ok(6_i32)).unwrap();
+        assert_eq!(*handle, 6);
+        assert_eq!(server.state::<i32>("data.bin"), Some(LoadState::Loaded));
+        assert!(server.load_with("data.bin", || Ok(String::new())).is_err());
+        assert!(matches!(
+            server.state_all("data.bin"),
+            Some(LoadState::Failed(_))
+        ));
+
+        server.release_all("data.bin");
+        assert_eq!(server.state::<String>("data.bin"), None);
+        assert_eq!(server.state_all("data.bin"), Some(LoadState::Loaded));
+    }
+
+    #[test]
+    fn invalidate_forgets_changed_files() {
+        let server = server();
+        let loads = Cell::new(0);
+        let load = || {
+            loads.set(loads.get() + 1);
+            Ok(loads.get())
+        };
+        let old = server.load_with("models/cube.obj", load).unwrap();
+        let other = server.load_with("models/ball.obj", load).unwrap();
+        // changed files are reported with absolute paths
+        server.invalidate(Path::new("/home/me/game/models/cube.obj"));
+        assert!(server.get::<i32>("models/cube.obj").is_none());
+        assert!(server.get::<i32>("models/ball.obj").is_some());
+        // handles loaded before still work
+        assert_eq!(*old, 1);
+
+        let new = server.load_with("models/cube.obj", load).unwrap();
+        assert_eq!(*new, 3);
+        assert!(!new.ptr_eq(&old));
+        assert_eq!(*other, 2);
+
+        // a failed load is forgotten too, so it's loaded again
+        server.load_async_with::<i32, _>("shaders/a.glsl", || Err(anyhow!("syntax error")));
+        server.wait::<i32>("shaders/a.glsl");
+        server.invalidate(Path::new("/home/me/game/shaders/a.glsl"));
+        assert_eq!(server.state::<i32>("shaders/a.glsl"), None);
+    }
+}
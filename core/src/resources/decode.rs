@@ -0,0 +1,100 @@
+use anyhow::{Context, Result};
+use image::RgbaImage;
+use std::io::Cursor;
+use std::path::Path;
+use tobj::{Material, Model};
+
+use super::loader::{Gltf, ResourceLoader};
+
+// Decode files of loaders that aren't on disk, e.g. packs or memory.
+// Files an obj or gltf refers to are read with the same loader.
+
+pub(crate) fn text_from_bytes(loader: &dyn ResourceLoader, name: &str) -> Result<String> {
+    let bytes = loader.load_bytes(name)?;
+    String::from_utf8(bytes).with_context(|| format!("{} is not utf-8 text", name))
+}
+
+// materials are relative to the obj file
+pub(crate) fn obj_from_bytes(
+    loader: &dyn ResourceLoader, name: &str,
+) -> Result<(Vec<Model>, Vec<Material>)> {
+    let dir = Path::new(name).parent().unwrap_or_else(|| Path::new(""));
+    let mut reader = Cursor::new(loader.load_bytes(name)?);
+    let (objs, materials) = tobj::load_obj_buf(&mut reader, true, |mtl| {
+        let bytes = loader
+            .load_bytes(&dir.join(mtl).to_string_lossy())
+            .map_err(|_| tobj::LoadError::OpenFileFailed)?;
+        tobj::load_mtl_buf(&mut Cursor::new(bytes))
+    })
+    .with_context(|| format!("Failed to load obj: {}", name))?;
+    Ok((objs, materials))
+}
+
+// a .gltf refers to buffers and images next to it, they're inlined
+// as data uris, so it's imported from memory like an embedded one
+pub(crate) fn gltf_from_bytes(loader: &dyn ResourceLoader, name: &str) -> Result<Gltf> {
+    let bytes = loader.load_bytes(name)?;
+    if bytes.starts_with(b"glTF") {
+        return gltf::import_slice(&bytes)
+            .with_context(|| format!("Failed to load gltf: {}", name));
+    }
+    let dir = Path::new(name).parent().unwrap_or_else(|| Path::new(""));
+    let mut json = gltf::Gltf::from_slice(&bytes)
+        .with_context(|| format!("Failed to load gltf: {}", name))?
+        .document
+        .into_json();
+    for buffer in json.buffers.iter_mut() {
+        if let Some(uri) = buffer.uri.as_mut() {
+            *uri = data_uri(loader, dir, uri, "application/octet-stream")?;
+        }
+    }
+    for image in json.images.iter_mut() {
+        if let Some(uri) = image.uri.as_mut() {
+            let mime = if uri.ends_with(".png") {
+                "image/png"
+            } else {
+                "image/jpeg"
+            };
+            *uri = data_uri(loader, dir, uri, mime)?;
+        }
+    }
+    let inlined = gltf::json::serialize::to_vec(&json)?;
+    gltf::import_slice(&inlined).with_context(|| format!("Failed to load gltf: {}", name))
+}
+
+pub(crate) fn rgba_from_bytes(loader: &dyn ResourceLoader, name: &str) -> Result<RgbaImage> {
+    let bytes = loader.load_bytes(name)?;
+    let img = image::load_from_memory(&bytes)
+        .with_context(|| format!("Failed to decode image {}", name))?;
+    Ok(img.to_rgba8())
+}
+
+// a file relative to `dir` as a data uri, others are kept
+fn data_uri(loader: &dyn ResourceLoader, dir: &Path, uri: &str, mime: &str) -> Result<String> {
+    if uri.contains(':') {
+        return Ok(uri.to_string());
+    }
+    let bytes = loader.load_bytes(&dir.join(uri).to_string_lossy())?;
+    Ok(format!("data:{};base64,{}", mime, base64(&bytes)))
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = ((b[0] as u32) << 16) | ((b[1] as u32) << 8) | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(CHARS[((n >> (18 - 6 * i)) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
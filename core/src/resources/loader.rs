@@ -6,10 +6,14 @@ use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
 use tobj::{Material, Model};
 
+use super::pack::PackLoader;
+use super::vfs::{MemoryLoader, Vfs, ENGINE, PROJECT};
 use crate::logger::logger;
 use slog::debug;
 
 static mut LOADER: Option<&dyn ResourceLoader> = None;
+// the loader of `init_loader`, more loaders are mounted into it
+static mut VFS: Option<&Vfs> = None;
 static INITIALIZED: AtomicBool = AtomicBool::new(false);
 
 fn set_singleton(loader: &'static dyn ResourceLoader) {
@@ -30,13 +34,49 @@ where
     }
 }
 
+// files of the project, and built in files under `engine://`
 pub fn init_loader(root: PathBuf) -> Result<()> {
-    let loader = Box::new(DefaultLoader::new(root));
-    set_singleton(Box::leak(loader));
+    let vfs = Vfs::new()
+        .mount(ENGINE, Box::new(MemoryLoader::engine()))
+        .mount(PROJECT, open_loader(root)?);
+    let vfs: &'static Vfs = Box::leak(Box::new(vfs));
+    set_singleton_inner(|| {
+        unsafe { VFS = Some(vfs) };
+        vfs
+    });
 
     Ok(())
 }
 
+// mount a loader into the loader of `init_loader`, e.g. a pack of
+// patches under `project://`; it overrides earlier mounts
+pub fn mount(scheme: &str, loader: Box<dyn ResourceLoader>) -> Result<()> {
+    unsafe {
+        match VFS {
+            Some(vfs) => {
+                vfs.add_mount(scheme, loader);
+                Ok(())
+            }
+            None => Err(anyhow!("loader not initialized with init_loader")),
+        }
+    }
+}
+
+// use another loader, e.g. files in memory in tests;
+// like `init_loader`, only the first call takes effect
+pub fn init_loader_with(loader: &'static dyn ResourceLoader) {
+    set_singleton(loader);
+}
+
+// a project is a directory, or a pack file of one
+pub fn open_loader(root: PathBuf) -> Result<Box<dyn ResourceLoader>> {
+    if root.is_file() {
+        Ok(Box::new(PackLoader::open(&root)?))
+    } else {
+        Ok(Box::new(DefaultLoader::new(root)))
+    }
+}
+
 pub fn loader() -> Result<&'static dyn ResourceLoader> {
     unsafe {
         if !std::ptr::read(&INITIALIZED).into_inner() {
@@ -46,13 +86,23 @@ pub fn loader() -> Result<&'static dyn ResourceLoader> {
     }
 }
 
-// Load resources
-pub trait ResourceLoader {
+// A glTF document with its buffers and images loaded
+pub type Gltf = (
+    gltf::Document,
+    Vec<gltf::buffer::Data>,
+    Vec<gltf::image::Data>,
+);
+
+// Load resources, shared with loader threads
+pub trait ResourceLoader: Send + Sync {
     fn load_text(&self, name: &str) -> Result<String>;
     fn load_bytes(&self, name: &str) -> Result<Vec<u8>>;
     // .obj can contain multiple models
     fn load_obj(&self, name: &str) -> Result<(Vec<Model>, Vec<Material>)>;
+    // .gltf or .glb, with external or embedded buffers and images
+    fn load_gltf(&self, name: &str) -> Result<Gltf>;
     fn load_rgba(&self, name: &str) -> Result<RgbaImage>;
+    fn exists(&self, name: &str) -> bool;
 }
 
 // Load from a base directory
@@ -97,10 +147,21 @@ impl ResourceLoader for DefaultLoader {
         Ok((objs, materials))
     }
 
+    fn load_gltf(&self, name: &str) -> Result<Gltf> {
+        let file_path = self.base_dir.join(name);
+        let gltf = gltf::import(&file_path)
+            .with_context(|| format!("Failed to load gltf: {:?}", &file_path))?;
+        Ok(gltf)
+    }
+
     fn load_rgba(&self, name: &str) -> Result<RgbaImage> {
         let file_path = self.base_dir.join(name);
         let img = image::open(&file_path)
             .with_context(|| format!("Failed to open file {:?}", &file_path))?;
         Ok(img.to_rgba8())
     }
+
+    fn exists(&self, name: &str) -> bool {
+        self.base_dir.join(name).exists()
+    }
 }
@@ -0,0 +1,186 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::input::ActionBindings;
+use crate::resources::loader;
+use crate::scheduler::LoopConfig;
+
+pub static MANIFEST: &str = "lucien.toml";
+
+// Project manifest `lucien.toml` in project root, e.g.
+//
+// name = "my game"
+// entry = "main"
+// scene = "scene.json"
+// frame_rate = 60
+// update_rate = 60
+// max_catch_up = 5
+// shader_dir = "shaders"
+//
+// [window]
+// title = "my game"
+// width = 1024
+// height = 768
+//
+// [actions]
+// jump = ["space", "w"]
+//
+// Every field is optional, missing fields use the defaults below.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Manifest {
+    pub name: String,
+    // entry script module, without `.wren`
+    pub entry: String,
+    // default scene file relative to project root
+    pub scene: String,
+    // target frames per second
+    pub frame_rate: u32,
+    // fixed updates per second
+    pub update_rate: u32,
+    // most fixed updates to run in a frame when the game falls behind
+    pub max_catch_up: u32,
+    // directory of shaders relative to project root
+    pub shader_dir: String,
+    pub window: WindowDesc,
+    // named actions for scripts, bound to keys and mouse buttons
+    pub actions: ActionBindings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowDesc {
+    pub title: String,
+    // logical size of window
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Manifest {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            window: WindowDesc {
+                title: name.to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    // load a manifest through the resource loader
+    pub fn load(name: &str) -> Result<Self> {
+        let text = loader()?.load_text(name)?;
+        let manifest =
+            toml::from_str(&text).with_context(|| format!("Failed to parse manifest {}", name))?;
+        Ok(manifest)
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string_pretty(self).context("Failed to serialize manifest")
+    }
+
+    pub fn loop_config(&self) -> LoopConfig {
+        LoopConfig {
+            frame_rate: self.frame_rate,
+            update_rate: self.update_rate,
+            max_catch_up: self.max_catch_up,
+        }
+    }
+}
+
+impl Default for Manifest {
+    fn default() -> Self {
+        Self {
+            name: "lucien".to_string(),
+            entry: "main".to_string(),
+            scene: "scene.json".to_string(),
+            frame_rate: 60,
+            update_rate: 60,
+            max_catch_up: 5,
+            shader_dir: "shaders".to_string(),
+            window: WindowDesc::default(),
+            actions: ActionBindings::new(),
+        }
+    }
+}
+
+impl Default for WindowDesc {
+    fn default() -> Self {
+        Self {
+            title: "lucien".to_string(),
+            width: 1024,
+            height: 768,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minimal_manifest_uses_defaults() {
+        let manifest: Manifest = toml::from_str(r#"name = "my game""#).unwrap();
+        assert_eq!(manifest.name, "my game");
+        assert_eq!(manifest.entry, "main");
+        assert_eq!(manifest.scene, "scene.json");
+        assert_eq!(manifest.loop_config(), LoopConfig::default());
+        assert_eq!(manifest.window.title, "lucien");
+        assert_eq!(manifest.window.width, 1024);
+        assert!(manifest.actions.is_empty());
+    }
+
+    #[test]
+    fn full_manifest() {
+        let manifest: Manifest = toml::from_str(
+            r#"
+name = "my game"
+entry = "game"
+scene = "levels/one.json"
+frame_rate = 30
+update_rate = 50
+max_catch_up = 3
+shader_dir = "glsl"
+
+[window]
+title = "My Game"
+width = 800
+
+[actions]
+jump = ["space", "w"]
+fire = ["mouse_left"]
+"#,
+        )
+        .unwrap();
+        assert_eq!(manifest.entry, "game");
+        assert_eq!(manifest.scene, "levels/one.json");
+        assert_eq!(manifest.shader_dir, "glsl");
+        let config = manifest.loop_config();
+        assert_eq!(config.frame_rate, 30);
+        assert_eq!(config.update_rate, 50);
+        assert_eq!(config.max_catch_up, 3);
+        assert_eq!(manifest.window.title, "My Game");
+        assert_eq!(manifest.window.width, 800);
+        // missing fields of a table use defaults too
+        assert_eq!(manifest.window.height, 768);
+        assert_eq!(manifest.actions["jump"], vec!["space", "w"]);
+        assert_eq!(manifest.actions["fire"], vec!["mouse_left"]);
+    }
+
+    #[test]
+    fn new_manifest_round_trips() {
+        let manifest = Manifest::new("demo");
+        assert_eq!(manifest.window.title, "demo");
+        let parsed: Manifest = toml::from_str(&manifest.to_toml().unwrap()).unwrap();
+        assert_eq!(parsed.name, "demo");
+        assert_eq!(parsed.window.title, "demo");
+        assert_eq!(parsed.loop_config(), manifest.loop_config());
+    }
+
+    #[test]
+    fn wrong_types_are_errors() {
+        assert!(toml::from_str::<Manifest>("frame_rate = \"fast\"").is_err());
+        assert!(toml::from_str::<Manifest>("[actions]\njump = \"space\"").is_err());
+    }
+}
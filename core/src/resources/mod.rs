@@ -1,5 +1,18 @@
+mod assets;
+mod decode;
 mod loader;
+mod manifest;
+mod pack;
+mod pool;
 mod project;
+mod scene;
+mod vfs;
 
+pub use assets::*;
 pub use loader::*;
+pub use manifest::*;
+pub use pack::*;
+pub use pool::*;
 pub use project::*;
+pub use scene::*;
+pub use vfs::*;
@@ -0,0 +1,453 @@
+use anyhow::{anyhow, Context, Result};
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use image::RgbaImage;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use tobj::{Material, Model};
+
+use super::decode::*;
+use super::loader::{Gltf, ResourceLoader};
+use crate::logger::logger;
+use slog::debug;
+
+// A pack is one file with every file of a project, e.g. `game.lpak`:
+//
+//   header: "LPAK", version: u32, index offset: u64
+//   blobs:  deflate compressed files
+//   index:  count: u32, then for each file
+//           name length: u32, name, offset: u64, packed size: u64,
+//           size: u64, has hash: u8, crc32 of the file: u32
+//
+// Numbers are little endian, names are relative to project root
+// and separated by `/`.
+pub const PACK_EXTENSION: &str = "lpak";
+const MAGIC: &[u8; 4] = b"LPAK";
+const VERSION: u32 = 1;
+
+#[derive(Debug, Clone)]
+struct PackEntry {
+    offset: u64,
+    packed_size: u64,
+    size: u64,
+    crc: Option<u32>,
+}
+
+// Files written into a pack
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PackStats {
+    pub files: usize,
+    pub size: u64,
+    pub packed_size: u64,
+}
+
+// Write every file under root into a pack, hidden files and other
+// packs are skipped; with `hashes`, files are checked when loaded
+pub fn write_pack(root: &Path, out: &Path, hashes: bool) -> Result<PackStats> {
+    let mut names = vec![];
+    collect_files(root, Path::new(""), &mut names)?;
+    // the pack may be written inside the project
+    let out_name = out.canonicalize().ok();
+    names.retain(|name| root.join(name).canonicalize().ok() != out_name);
+    names.sort();
+
+    let file = File::create(out).with_context(|| format!("Failed to create pack {:?}", out))?;
+    let mut writer = BufWriter::new(file);
+    let mut stats = PackStats::default();
+    let mut index = vec![];
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    // index offset is written when it's known
+    writer.write_all(&0u64.to_le_bytes())?;
+    let mut offset = (MAGIC.len() + 4 + 8) as u64;
+    for name in names.iter() {
+        let path = root.join(name);
+        let data =
+            std::fs::read(&path).with_context(|| format!("Failed to read file {:?}", &path))?;
+        let mut encoder = DeflateEncoder::new(vec![], Compression::default());
+        encoder.write_all(&data)?;
+        let packed = encoder.finish()?;
+        writer.write_all(&packed)?;
+
+        let entry = PackEntry {
+            offset,
+            packed_size: packed.len() as u64,
+            size: data.len() as u64,
+            crc: if hashes { Some(crc32(&data)) } else { None },
+        };
+        offset += entry.packed_size;
+        stats.files += 1;
+        stats.size += entry.size;
+        stats.packed_size += entry.packed_size;
+        index.push((name, entry));
+    }
+
+    writer.write_all(&(index.len() as u32).to_le_bytes())?;
+    for (name, entry) in index.iter() {
+        writer.write_all(&(name.len() as u32).to_le_bytes())?;
+        writer.write_all(name.as_bytes())?;
+        writer.write_all(&entry.offset.to_le_bytes())?;
+        writer.write_all(&entry.packed_size.to_le_bytes())?;
+        writer.write_all(&entry.size.to_le_bytes())?;
+        writer.write_all(&[entry.crc.is_some() as u8])?;
+        writer.write_all(&entry.crc.unwrap_or(0).to_le_bytes())?;
+    }
+    writer.seek(SeekFrom::Start((MAGIC.len() + 4) as u64))?;
+    writer.write_all(&offset.to_le_bytes())?;
+    writer
+        .flush()
+        .with_context(|| format!("Failed to write pack {:?}", out))?;
+    debug!(logger(), "packed {} files into {:?}", stats.files, out);
+
+    Ok(stats)
+}
+
+// names of files under `dir`, relative to root
+fn collect_files(root: &Path, dir: &Path, names: &mut Vec<String>) -> Result<()> {
+    let path = root.join(dir);
+    let entries =
+        std::fs::read_dir(&path).with_context(|| format!("Failed to read directory {:?}", path))?;
+    for entry in entries {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if file_name.starts_with('.') {
+            continue;
+        }
+        let relative = dir.join(&file_name);
+        if entry.file_type()?.is_dir() {
+            collect_files(root, &relative, names)?;
+        } else if !file_name.ends_with(&format!(".{}", PACK_EXTENSION)) {
+            names.push(normalize(&relative.to_string_lossy()));
+        }
+    }
+    Ok(())
+}
+
+// `a/./b/../c` is `a/c`, the way names are stored in a pack
+pub(crate) fn normalize(name: &str) -> String {
+    let mut parts: Vec<String> = vec![];
+    for component in Path::new(name).components() {
+        match component {
+            Component::Normal(part) => parts.push(part.to_string_lossy().to_string()),
+            Component::ParentDir => {
+                parts.pop();
+            }
+            _ => {}
+        }
+    }
+    parts.join("/")
+}
+
+// Load from a pack file, like loose files in a project directory
+#[derive(Debug)]
+pub struct PackLoader {
+    path: PathBuf,
+    file: Mutex<BufReader<File>>,
+    entries: HashMap<String, PackEntry>,
+}
+
+impl PackLoader {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path).with_context(|| format!("Failed to open pack {:?}", path))?;
+        let mut reader = BufReader::new(file);
+        let entries =
+            read_index(&mut reader).with_context(|| format!("Failed to read pack {:?}", path))?;
+        debug!(logger(), "pack {:?} opened, {} files", path, entries.len());
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: Mutex::new(reader),
+            entries,
+        })
+    }
+
+    // names of files in the pack
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(|name| name.as_str())
+    }
+
+    fn read(&self, name: &str) -> Result<Vec<u8>> {
+        let entry = self
+            .entries
+            .get(&normalize(name))
+            .ok_or_else(|| anyhow!("{} not found in pack {:?}", name, self.path))?;
+        let mut packed = vec![0; entry.packed_size as usize];
+        {
+            let mut file = self.file.lock().unwrap();
+            file.seek(SeekFrom::Start(entry.offset))?;
+            file.read_exact(&mut packed)?;
+        }
+        // a wrong size in the index can't make it decode without end
+        let mut data = vec![];
+        DeflateDecoder::new(&packed[..])
+            .take(entry.size.saturating_add(1))
+            .read_to_end(&mut data)?;
+        if data.len() as u64 != entry.size {
+            return Err(anyhow!("{} has a wrong size", name));
+        }
+        if let Some(crc) = entry.crc {
+            if crc32(&data) != crc {
+                return Err(anyhow!("{} doesn't match its hash", name));
+            }
+        }
+        Ok(data)
+    }
+}
+
+// lengths and offsets are checked against the file,
+// so a broken index doesn't allocate more than the file size
+fn read_index<R: Read + Seek>(reader: &mut R) -> Result<HashMap<String, PackEntry>> {
+    let len = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(0))?;
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(anyhow!("not a pack file"));
+    }
+    let version = read_u32(reader)?;
+    if version != VERSION {
+        return Err(anyhow!("unsupported pack version {}", version));
+    }
+    let index_offset = read_u64(reader)?;
+    if index_offset > len {
+        return Err(anyhow!("index is past the end of the pack"));
+    }
+    reader.seek(SeekFrom::Start(index_offset))?;
+
+    let count = read_u32(reader)?;
+    let mut entries = HashMap::new();
+    for _ in 0..count {
+        let name_len = read_u32(reader)? as u64;
+        if name_len > len - index_offset {
+            return Err(anyhow!("file name is past the end of the pack"));
+        }
+        let mut name = vec![0; name_len as usize];
+        reader.read_exact(&mut name)?;
+        let name = String::from_utf8(name).context("File name is not utf-8")?;
+        let offset = read_u64(reader)?;
+        let packed_size = read_u64(reader)?;
+        let size = read_u64(reader)?;
+        let mut has_crc = [0; 1];
+        reader.read_exact(&mut has_crc)?;
+        let crc = read_u32(reader)?;
+        // files are stored before the index
+        match offset.checked_add(packed_size) {
+            Some(end) if end <= index_offset => {}
+            _ => return Err(anyhow!("{} is past the end of the pack", name)),
+        }
+        let entry = PackEntry {
+            offset,
+            packed_size,
+            size,
+            crc: if has_crc[0] != 0 { Some(crc) } else { None },
+        };
+        entries.insert(name, entry);
+    }
+    Ok(entries)
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(data);
+    hasher.finalize()
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+impl ResourceLoader for PackLoader {
+    fn load_text(&self, name: &str) -> Result<String> {
+        text_from_bytes(self, name)
+    }
+
+    fn load_bytes(&self, name: &str) -> Result<Vec<u8>> {
+        self.read(name)
+            .with_context(|| format!("Failed to read {} from pack", name))
+    }
+
+    fn load_obj(&self, name: &str) -> Result<(Vec<Model>, Vec<Material>)> {
+        obj_from_bytes(self, name)
+    }
+
+    fn load_gltf(&self, name: &str) -> Result<Gltf> {
+        gltf_from_bytes(self, name)
+    }
+
+    fn load_rgba(&self, name: &str) -> Result<RgbaImage> {
+        rgba_from_bytes(self, name)
+    }
+
+    fn exists(&self, name: &str) -> bool {
+        self.entries.contains_key(&normalize(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a directory removed with everything in it when dropped
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("lucien-{}-{}", name, std::process::id()));
+            std::fs::create_dir_all(&dir).unwrap();
+            TempDir(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    // a project of files packed with hashes, returns the pack path
+    fn pack(dir: &TempDir, files: &[(&str, &[u8])]) -> PathBuf {
+        let root = dir.0.join("project");
+        for (name, bytes) in files.iter() {
+            let path = root.join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, bytes).unwrap();
+        }
+        let out = dir.0.join("project.lpak");
+        write_pack(&root, &out, true).unwrap();
+        out
+    }
+
+    fn open_error(path: &Path) -> String {
+        format!("{:#}", PackLoader::open(path).unwrap_err())
+    }
+
+    #[test]
+    fn files_round_trip() {
+        let dir = TempDir::new("pack-round-trip");
+        let cube = vec![7u8; 4096];
+        let out = pack(
+            &dir,
+            &[
+                ("scripts/main.wren", b"var x = 1"),
+                ("models/cube.obj", &cube),
+                (".hidden", b"hidden"),
+                ("old.lpak", b"old pack"),
+            ],
+        );
+
+        let pack = PackLoader::open(&out).unwrap();
+        let mut names: Vec<&str> = pack.names().collect();
+        names.sort();
+        assert_eq!(names, vec!["models/cube.obj", "scripts/main.wren"]);
+        assert_eq!(pack.load_text("scripts/main.wren").unwrap(), "var x = 1");
+        assert_eq!(
+            pack.load_bytes("./models/../models/cube.obj").unwrap(),
+            cube
+        );
+        assert!(!pack.exists(".hidden"));
+        assert!(pack.load_bytes("missing.txt").is_err());
+    }
+
+    #[test]
+    fn hash_mismatch_is_an_error() {
+        let dir = TempDir::new("pack-hash");
+        let out = pack(&dir, &[("main.wren", b"var x = 1")]);
+        // crc of the only file is the last field of the pack
+        let mut bytes = std::fs::read(&out).unwrap();
+        *bytes.last_mut().unwrap() ^= 0xff;
+        std::fs::write(&out, bytes).unwrap();
+
+        let pack = PackLoader::open(&out).unwrap();
+        let error = format!("{:#}", pack.load_bytes("main.wren").unwrap_err());
+        assert!(error.contains("doesn't match its hash"), "{}", error);
+    }
+
+    #[test]
+    fn corrupted_lengths_are_errors() {
+        let dir = TempDir::new("pack-lengths");
+        let out = pack(&dir, &[("main.wren", b"var x = 1")]);
+        let bytes = std::fs::read(&out).unwrap();
+        let mut offset = [0; 8];
+        offset.copy_from_slice(&bytes[8..16]);
+        // after the count of files: name length, name, offset, packed size, size
+        let index = u64::from_le_bytes(offset) as usize;
+        let name_len = index + 4;
+        let packed_size = name_len + 4 + "main.wren".len() + 8;
+        let size = packed_size + 8;
+
+        let corrupt = |at: usize, value: &[u8]| {
+            let mut bytes = bytes.clone();
+            bytes[at..at + value.len()].copy_from_slice(value);
+            std::fs::write(&out, bytes).unwrap();
+        };
+        corrupt(name_len, &u32::MAX.to_le_bytes());
+        let error = open_error(&out);
+        assert!(error.contains("file name is past the end"), "{}", error);
+
+        corrupt(packed_size, &u64::MAX.to_le_bytes());
+        let error = open_error(&out);
+        assert!(error.contains("main.wren is past the end"), "{}", error);
+
+        corrupt(8, &u64::MAX.to_le_bytes());
+        let error = open_error(&out);
+        assert!(error.contains("index is past the end"), "{}", error);
+
+        // a wrong size is found when the file is read
+        corrupt(size, &u64::MAX.to_le_bytes());
+        let pack = PackLoader::open(&out).unwrap();
+        let error = format!("{:#}", pack.load_bytes("main.wren").unwrap_err());
+        assert!(error.contains("has a wrong size"), "{}", error);
+    }
+
+    #[test]
+    fn wrong_magic_is_not_a_pack() {
+        let dir = TempDir::new("pack-magic");
+        let out = dir.0.join("fake.lpak");
+        std::fs::write(&out, b"ZIP!0000000000000000").unwrap();
+        let error = open_error(&out);
+        assert!(error.contains("not a pack file"), "{}", error);
+    }
+
+    #[test]
+    fn wrong_version_is_unsupported() {
+        let dir = TempDir::new("pack-version");
+        let out = pack(&dir, &[("main.wren", b"var x = 1")]);
+        let mut bytes = std::fs::read(&out).unwrap();
+        bytes[4..8].copy_from_slice(&2u32.to_le_bytes());
+        std::fs::write(&out, bytes).unwrap();
+        let error = open_error(&out);
+        assert!(error.contains("unsupported pack version 2"), "{}", error);
+    }
+
+    #[test]
+    fn truncated_index_is_an_error() {
+        let dir = TempDir::new("pack-truncated");
+        let out = pack(&dir, &[("main.wren", b"var x = 1")]);
+        let bytes = std::fs::read(&out).unwrap();
+        std::fs::write(&out, &bytes[..bytes.len() - 6]).unwrap();
+        let error = open_error(&out);
+        assert!(error.contains("Failed to read pack"), "{}", error);
+    }
+
+    #[test]
+    fn names_are_normalized() {
+        assert_eq!(normalize("a/./b/../c"), "a/c");
+        assert_eq!(normalize("./scripts/../main.wren"), "main.wren");
+        assert_eq!(normalize("a/b/../../c"), "c");
+        // names can't climb out of the root
+        assert_eq!(normalize("../../a"), "a");
+        assert_eq!(normalize("a/../.."), "");
+    }
+}
@@ -0,0 +1,55 @@
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::logger::logger;
+use slog::debug;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+// Worker threads that load resources in background. Jobs run in the
+// order they're spawned; workers stop when the pool is dropped.
+#[derive(Debug)]
+pub struct LoadPool {
+    sender: Mutex<Sender<Job>>,
+    workers: usize,
+}
+
+impl LoadPool {
+    pub fn new(workers: usize) -> Self {
+        let (sender, receiver) = channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for i in 0..workers {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name(format!("lucien loader {}", i))
+                .spawn(move || loop {
+                    // the lock is released before the job runs
+                    let job = match receiver.lock().unwrap().recv() {
+                        Ok(job) => job,
+                        Err(_) => break,
+                    };
+                    job();
+                })
+                .expect("Failed to spawn loader thread");
+        }
+        debug!(logger(), "load pool started with {} workers", workers);
+
+        Self {
+            sender: Mutex::new(sender),
+            workers,
+        }
+    }
+
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    pub fn spawn<F>(&self, job: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        // workers only stop with the pool, sending can't fail
+        let _ = self.sender.lock().unwrap().send(Box::new(job));
+    }
+}
@@ -1,19 +1,73 @@
 use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, Context, Result};
-use slog::{error, info};
+use slog::{error, info, warn};
 
 use crate::logger::logger;
-use crate::resources::loader;
+use crate::resources::{loader, Manifest, SceneDesc, MANIFEST};
+
+// files written into a new project, so it runs right away
+static STARTER_SCRIPT: &str = r##"import "graphics" for Graphics
+import "time" for Time
+
+var start = Fn.new {
+    System.print("Hello from wren!")
+}
+
+// dt is seconds since last update
+var update = Fn.new { |dt| }
+"##;
+static STARTER_SCENE: &str = r##"{
+  "camera": {
+    "eye": [2.0, 2.0, 4.0],
+    "look_at": [0.0, 0.0, 0.0]
+  },
+  "lights": [
+    { "type": "point", "position": [2.0, 3.0, 2.0], "color": [1.0, 1.0, 1.0] }
+  ],
+  "meshes": [
+    { "path": "cube.obj" }
+  ]
+}
+"##;
+static STARTER_FILES: &[(&str, &str)] = &[
+    ("cube.obj", include_str!("templates/cube.obj")),
+    ("cube.mtl", include_str!("templates/cube.mtl")),
+    (
+        "normal.vert.glsl",
+        include_str!("templates/normal.vert.glsl"),
+    ),
+    (
+        "normal.frag.glsl",
+        include_str!("templates/normal.frag.glsl"),
+    ),
+    ("lights.glsl", include_str!("templates/lights.glsl")),
+];
 
 // Create or load a project under a directory
 pub struct Project {
     // project root directory
     base_dir: Option<PathBuf>,
+    // settings in `lucien.toml`
+    manifest: Manifest,
 }
+
+impl Default for Project {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Project {
     pub fn new() -> Self {
-        Self { base_dir: None }
+        Self {
+            base_dir: None,
+            manifest: Manifest::default(),
+        }
+    }
+
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
     }
 
     pub fn path(&self, name: &str) -> Option<PathBuf> {
@@ -32,12 +86,12 @@ impl Project {
                 abs
             });
         }
-        let abs = std::env::current_dir().unwrap();
-        let res = abs.join(relative).canonicalize();
-        match res {
-            Ok(_) => Some(res.unwrap()),
-            Err(_) => None,
+        let abs = std::env::current_dir().unwrap().join(relative);
+        // a new project doesn't exist yet, so it can't be canonicalized
+        if !abs.exists() {
+            return Some(abs);
         }
+        abs.canonicalize().ok()
     }
 
     // create project or load from existing
@@ -45,24 +99,91 @@ impl Project {
         let root = self.absolute_path().unwrap();
         if !root.exists() {
             // create project directory
-            match std::fs::create_dir(&root) {
+            match Project::scaffold(&root) {
                 Ok(_) => {
                     info!(logger(), "project created at: {:?}", root);
                 }
-                Err(_) => {
-                    error!(logger(), "project creation error: {:?}", root);
+                Err(e) => {
+                    error!(logger(), "project creation error: {:?}, {:?}", root, e);
                     return Err(anyhow!("failed to create project"));
                 }
             }
         } else {
             info!(logger(), "project loaded from: {:?}", root);
         }
+        self.open(root)
+    }
+
+    // load an existing project, it's an error if there's none
+    pub fn load(&mut self) -> Result<()> {
+        let root = self.absolute_path().unwrap();
+        if !root.exists() {
+            return Err(anyhow!("project not found at {:?}", root));
+        }
+        info!(logger(), "project loaded from: {:?}", root);
+        self.open(root)
+    }
+
+    fn open(&mut self, root: PathBuf) -> Result<()> {
         // initialize loader with the root directory
         loader::init_loader(root).context("Failed to init resource loader")?;
+        // projects created before manifest was introduced don't have one
+        if loader()?.exists(MANIFEST) {
+            self.manifest = Manifest::load(MANIFEST).context("Failed to load manifest")?;
+        } else {
+            warn!(logger(), "{} not found, use default settings", MANIFEST);
+        }
 
         Ok(())
     }
 
+    // write manifest, entry script, scene and default shaders
+    // to a new project directory
+    fn scaffold(root: &Path) -> Result<()> {
+        let name = root
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("lucien");
+        let manifest = Manifest::new(name);
+        let shader_dir = root.join(&manifest.shader_dir);
+        std::fs::create_dir_all(&shader_dir)
+            .with_context(|| format!("Failed to create directory {:?}", &shader_dir))?;
+
+        let mut files = vec![
+            (root.join(MANIFEST), manifest.to_toml()?),
+            (
+                root.join(format!("{}.wren", manifest.entry)),
+                STARTER_SCRIPT.to_string(),
+            ),
+            (root.join(&manifest.scene), STARTER_SCENE.to_string()),
+        ];
+        for (name, contents) in STARTER_FILES.iter() {
+            let dir = if name.ends_with(".glsl") {
+                &shader_dir
+            } else {
+                root
+            };
+            files.push((dir.join(name), contents.to_string()));
+        }
+        for (path, contents) in files.iter() {
+            std::fs::write(path, contents)
+                .with_context(|| format!("Failed to write file {:?}", path))?;
+        }
+
+        Ok(())
+    }
+
+    // load scene file of the project, if there's no scene file
+    // an empty scene is used
+    pub fn load_scene(&self) -> Result<SceneDesc> {
+        let scene = &self.manifest.scene;
+        if !loader()?.exists(scene) {
+            warn!(logger(), "scene file {} not found, use empty scene", scene);
+            return Ok(SceneDesc::default());
+        }
+        SceneDesc::load(scene).context("Failed to load scene")
+    }
+
     // change base directory
     pub fn base_dir<P: AsRef<Path>>(mut self, base_dir: P) -> Self {
         self.base_dir = Some(base_dir.as_ref().into());
@@ -0,0 +1,265 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::resources::loader;
+
+// Declarative scene file, e.g. `scene.json`. Every field is optional,
+// missing fields use the defaults below. Vectors are [x, y, z],
+// colors are linear [r, g, b] or [r, g, b, a].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneDesc {
+    pub camera: CameraDesc,
+    pub clear_color: [f64; 4],
+    pub ambient_light: AmbientLightDesc,
+    pub lights: Vec<LightDesc>,
+    pub meshes: Vec<MeshDesc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraDesc {
+    pub eye: [f32; 3],
+    pub look_at: [f32; 3],
+    pub up: [f32; 3],
+    // vertical field of view in radians
+    pub fov: f32,
+    pub z_near: f32,
+    pub z_far: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AmbientLightDesc {
+    pub color: [f32; 3],
+    pub intensity: f32,
+}
+
+// Lights are tagged by type, e.g.
+// { "type": "point", "position": [0, 1, 0], "color": [1, 1, 1] }
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum LightDesc {
+    Point {
+        position: [f32; 3],
+        color: [f32; 3],
+        #[serde(default = "one")]
+        intensity: f32,
+    },
+    Directional {
+        direction: [f32; 3],
+        color: [f32; 3],
+        #[serde(default = "one")]
+        intensity: f32,
+    },
+    Spot {
+        position: [f32; 3],
+        direction: [f32; 3],
+        color: [f32; 3],
+        #[serde(default = "one")]
+        intensity: f32,
+        // cone half angle in radians
+        angle: f32,
+    },
+}
+
+// A node in scene, with a mesh file to load, relative to project root.
+// Path can be empty to group children. Transform is relative to parent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MeshDesc {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub path: String,
+    #[serde(default)]
+    pub transform: TransformDesc,
+    // overrides materials in the mesh file
+    #[serde(default)]
+    pub material: Option<MaterialDesc>,
+    // how normals are computed if the mesh file has none
+    #[serde(default)]
+    pub normals: Normals,
+    #[serde(default)]
+    pub children: Vec<MeshDesc>,
+}
+
+// Smooth normals are averaged over faces sharing a vertex, weighted by
+// face area; flat normals are the face normal, so edges look sharp.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Normals {
+    #[default]
+    Smooth,
+    Flat,
+}
+
+// Rotation is euler angles in degrees, applied in y, x, z order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransformDesc {
+    pub translation: [f32; 3],
+    pub rotation: [f32; 3],
+    pub scale: [f32; 3],
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaterialDesc {
+    pub ambient: [f32; 3],
+    pub diffuse: [f32; 3],
+    pub specular: [f32; 3],
+    pub shininess: f32,
+    // image relative to project root
+    pub diffuse_texture: Option<String>,
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+impl SceneDesc {
+    // load a scene file through the resource loader
+    pub fn load(name: &str) -> Result<Self> {
+        let text = loader()?.load_text(name)?;
+        let desc = serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse scene file {}", name))?;
+        Ok(desc)
+    }
+}
+
+impl Default for SceneDesc {
+    fn default() -> Self {
+        Self {
+            camera: CameraDesc::default(),
+            clear_color: [0.1, 0.1, 0.1, 1.0],
+            ambient_light: AmbientLightDesc::default(),
+            lights: vec![LightDesc::Point {
+                position: [0.7, 0.0, 2.0],
+                color: [0.1, 0.1, 0.1],
+                intensity: 1.0,
+            }],
+            meshes: vec![],
+        }
+    }
+}
+
+impl Default for CameraDesc {
+    fn default() -> Self {
+        Self {
+            eye: [0.0, 1.0, 2.0],
+            look_at: [0.0, 0.0, 0.0],
+            up: [0.0, 1.0, 0.0],
+            fov: 0.7,
+            z_near: 0.1,
+            z_far: 100.0,
+        }
+    }
+}
+
+impl Default for AmbientLightDesc {
+    fn default() -> Self {
+        Self {
+            color: [1.0, 1.0, 1.0],
+            intensity: 1.0,
+        }
+    }
+}
+
+impl Default for TransformDesc {
+    fn default() -> Self {
+        Self {
+            translation: [0.0, 0.0, 0.0],
+            rotation: [0.0, 0.0, 0.0],
+            scale: [1.0, 1.0, 1.0],
+        }
+    }
+}
+
+impl Default for MaterialDesc {
+    fn default() -> Self {
+        Self {
+            ambient: [1.0, 1.0, 1.0],
+            diffuse: [1.0, 1.0, 1.0],
+            specular: [0.5, 0.5, 0.5],
+            shininess: 0.0,
+            diffuse_texture: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static EXAMPLE: &str = include_str!("../../../src/examples/scene.json");
+
+    #[test]
+    fn example_scene_round_trips() {
+        let desc: SceneDesc = serde_json::from_str(EXAMPLE).unwrap();
+        assert_eq!(desc.camera.eye, [0.0, 2.0, 5.0]);
+        // missing fields use defaults
+        assert_eq!(desc.camera.up, [0.0, 1.0, 0.0]);
+        assert_eq!(desc.clear_color, [0.1, 0.1, 0.1, 1.0]);
+        assert_eq!(desc.lights.len(), 2);
+        match &desc.lights[1] {
+            LightDesc::Directional {
+                direction,
+                intensity,
+                ..
+            } => {
+                assert_eq!(*direction, [-1.0, -1.0, -1.0]);
+                assert_eq!(*intensity, 1.0);
+            }
+            light => panic!("expected a directional light, got {:?}", light),
+        }
+
+        assert_eq!(desc.meshes.len(), 2);
+        let cube = &desc.meshes[1];
+        assert_eq!(cube.name, "cube");
+        assert_eq!(cube.transform.rotation, [0.0, 45.0, 0.0]);
+        assert_eq!(cube.normals, Normals::Smooth);
+        assert_eq!(cube.children.len(), 1);
+        assert_eq!(cube.children[0].name, "moon");
+        assert_eq!(cube.children[0].transform.scale, [0.4, 0.4, 0.4]);
+        assert_eq!(desc.meshes[0].transform.scale, [1.0, 1.0, 1.0]);
+
+        let json = serde_json::to_value(&desc).unwrap();
+        let again: SceneDesc = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&again).unwrap(), json);
+    }
+
+    #[test]
+    fn lights_are_tagged_by_type() {
+        let desc: SceneDesc = serde_json::from_str(
+            r#"{
+  "lights": [
+    { "type": "spot", "position": [0, 2, 0], "direction": [0, -1, 0],
+      "color": [1, 1, 1], "intensity": 2.0, "angle": 0.5 }
+  ],
+  "meshes": [{ "path": "flat.obj", "normals": "flat" }]
+}"#,
+        )
+        .unwrap();
+        match &desc.lights[0] {
+            LightDesc::Spot {
+                intensity, angle, ..
+            } => {
+                assert_eq!(*intensity, 2.0);
+                assert_eq!(*angle, 0.5);
+            }
+            light => panic!("expected a spot light, got {:?}", light),
+        }
+        assert_eq!(desc.meshes[0].normals, Normals::Flat);
+        assert!(
+            serde_json::from_str::<SceneDesc>(r#"{ "lights": [{ "type": "area" }] }"#).is_err()
+        );
+    }
+
+    #[test]
+    fn empty_scene_uses_defaults() {
+        let desc: SceneDesc = serde_json::from_str("{}").unwrap();
+        assert_eq!(desc.lights.len(), 1);
+        assert!(desc.meshes.is_empty());
+        assert_eq!(desc.camera.fov, 0.7);
+    }
+}
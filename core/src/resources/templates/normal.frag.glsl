@@ -0,0 +1,42 @@
+#version 450
+
+layout(set=0, binding=0) uniform Uniforms {
+  // camera data
+  mat4 view_proj;
+  vec3 cam_pos;
+  vec3 cam_dir;
+  // ambient light
+  vec3 al_color;
+  float al_intensity;
+};
+// texture data
+layout(set=1, binding=0) uniform texture2D t_diffuse;
+layout(set=1, binding=1) uniform sampler s_diffuse;
+// material data
+layout(set=2, binding=0) uniform Material {
+  vec3 u_ambient;
+  vec3 u_diffuse;
+  vec3 u_specular;
+  float u_shininess;
+};
+#include "lights.glsl"
+
+layout(location=0) in vec3 v_position;
+layout(location=1) in vec3 v_normal;
+layout(location=2) in vec2 v_tex_coord;
+
+layout(location=0) out vec4 f_color;
+
+void main() {
+  // pixel is behind camera, we hide it
+  vec3 view_dir = normalize(cam_pos - v_position);
+  if (dot(view_dir, cam_dir) > 0.0) { discard; }
+
+  vec3 normal = normalize(v_normal);
+  vec4 obj_color = vec4(normal, 1.0) * 1.5;
+  vec3 ambient = u_ambient * 0.5 + al_color * al_intensity * 0.5;
+  vec3 lighting = blinn_phong(v_position, normal, view_dir, u_diffuse, u_specular, u_shininess);
+
+  vec3 result = (ambient * 0.2 + lighting * 0.8) * obj_color.xyz;
+  f_color = vec4(result, obj_color.a);
+}
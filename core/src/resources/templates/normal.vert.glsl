@@ -0,0 +1,32 @@
+#version 450
+
+// albedo
+layout(location=0) in vec3 a_position;
+layout(location=1) in vec3 a_normal;
+layout(location=2) in vec2 a_tex_coord;
+// instance: model matrix, locations 0 to 7 are for vertex attributes
+layout(location=8) in vec4 a_model_0;
+layout(location=9) in vec4 a_model_1;
+layout(location=10) in vec4 a_model_2;
+layout(location=11) in vec4 a_model_3;
+// uniforms: camera data
+layout(set=0, binding=0) uniform Uniforms {
+  mat4 view_proj;
+  vec3 cam_pos;
+  vec3 cam_dir;
+};
+
+layout(location=0) out vec3 v_position;
+layout(location=1) out vec3 v_normal;
+layout(location=2) out vec2 v_tex_coord;
+
+void main() {
+  mat4 model = mat4(a_model_0, a_model_1, a_model_2, a_model_3);
+  // keep normals perpendicular to surface after non uniform scale
+  mat3 normal_matrix = transpose(inverse(mat3(model)));
+  vec4 world_position = model * vec4(a_position, 1.0);
+  gl_Position = view_proj * world_position;
+  v_position = world_position.xyz;
+  v_normal = normalize(normal_matrix * a_normal);
+  v_tex_coord = a_tex_coord;
+}
@@ -0,0 +1,300 @@
+use anyhow::{anyhow, Result};
+use image::RgbaImage;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use tobj::{Material, Model};
+
+use super::decode::*;
+use super::loader::{Gltf, ResourceLoader};
+use super::pack::normalize;
+
+// Schemes of names, e.g. `engine://textures/blank.png`;
+// a name without scheme is a file of the project
+pub const ENGINE: &str = "engine";
+pub const PROJECT: &str = "project";
+
+// files built in the engine, mounted under `engine://`
+static ENGINE_FILES: &[(&str, &[u8])] = &[(
+    "textures/blank.png",
+    include_bytes!("engine/textures/blank.png"),
+)];
+
+// Files in memory, e.g. built in files or files of tests
+#[derive(Debug, Default)]
+pub struct MemoryLoader {
+    files: RwLock<HashMap<String, Vec<u8>>>,
+}
+
+impl MemoryLoader {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    // files built in the engine
+    pub fn engine() -> Self {
+        ENGINE_FILES
+            .iter()
+            .fold(MemoryLoader::new(), |loader, (name, bytes)| {
+                loader.file(name, *bytes)
+            })
+    }
+
+    // add a file
+    pub fn file<B: Into<Vec<u8>>>(self, name: &str, bytes: B) -> Self {
+        self.insert(name, bytes);
+        self
+    }
+
+    // add or replace a file
+    pub fn insert<B: Into<Vec<u8>>>(&self, name: &str, bytes: B) {
+        let mut files = self.files.write().unwrap();
+        files.insert(normalize(name), bytes.into());
+    }
+
+    pub fn remove(&self, name: &str) -> bool {
+        let mut files = self.files.write().unwrap();
+        files.remove(&normalize(name)).is_some()
+    }
+}
+
+impl ResourceLoader for MemoryLoader {
+    fn load_text(&self, name: &str) -> Result<String> {
+        text_from_bytes(self, name)
+    }
+
+    fn load_bytes(&self, name: &str) -> Result<Vec<u8>> {
+        let files = self.files.read().unwrap();
+        files
+            .get(&normalize(name))
+            .cloned()
+            .ok_or_else(|| anyhow!("{} not found in memory", name))
+    }
+
+    fn load_obj(&self, name: &str) -> Result<(Vec<Model>, Vec<Material>)> {
+        obj_from_bytes(self, name)
+    }
+
+    fn load_gltf(&self, name: &str) -> Result<Gltf> {
+        gltf_from_bytes(self, name)
+    }
+
+    fn load_rgba(&self, name: &str) -> Result<RgbaImage> {
+        rgba_from_bytes(self, name)
+    }
+
+    fn exists(&self, name: &str) -> bool {
+        let files = self.files.read().unwrap();
+        files.contains_key(&normalize(name))
+    }
+}
+
+struct Mount {
+    scheme: String,
+    loader: Arc<dyn ResourceLoader>,
+}
+
+// Loaders mounted under schemes. A file is loaded from the last
+// mount of its scheme that has it, so later mounts override earlier
+// ones, e.g. a pack of patches mounted over the project.
+#[derive(Default)]
+pub struct Vfs {
+    mounts: RwLock<Vec<Mount>>,
+}
+
+impl Vfs {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn mount(self, scheme: &str, loader: Box<dyn ResourceLoader>) -> Self {
+        self.add_mount(scheme, loader);
+        self
+    }
+
+    // mount while files are loaded, e.g. into the loader singleton
+    pub fn add_mount(&self, scheme: &str, loader: Box<dyn ResourceLoader>) {
+        let mut mounts = self.mounts.write().unwrap();
+        mounts.push(Mount {
+            scheme: scheme.to_string(),
+            loader: loader.into(),
+        });
+    }
+
+    // loader of a name, and the name in it
+    fn resolve<'a>(&self, name: &'a str) -> Result<(Arc<dyn ResourceLoader>, &'a str)> {
+        let (scheme, path) = match name.find("://") {
+            Some(i) => (&name[..i], &name[i + 3..]),
+            None => (PROJECT, name),
+        };
+        let mounts = self.mounts.read().unwrap();
+        let mut mounts = mounts
+            .iter()
+            .rev()
+            .filter(|mount| mount.scheme == scheme)
+            .peekable();
+        if mounts.peek().is_none() {
+            return Err(anyhow!("nothing is mounted at {}://", scheme));
+        }
+        mounts
+            .find(|mount| mount.loader.exists(path))
+            .map(|mount| (mount.loader.clone(), path))
+            .ok_or_else(|| anyhow!("{} not found", name))
+    }
+}
+
+impl ResourceLoader for Vfs {
+    fn load_text(&self, name: &str) -> Result<String> {
+        let (loader, path) = self.resolve(name)?;
+        loader.load_text(path)
+    }
+
+    fn load_bytes(&self, name: &str) -> Result<Vec<u8>> {
+        let (loader, path) = self.resolve(name)?;
+        loader.load_bytes(path)
+    }
+
+    // files the mesh refers to are loaded from the same mount
+    fn load_obj(&self, name: &str) -> Result<(Vec<Model>, Vec<Material>)> {
+        let (loader, path) = self.resolve(name)?;
+        loader.load_obj(path)
+    }
+
+    fn load_gltf(&self, name: &str) -> Result<Gltf> {
+        let (loader, path) = self.resolve(name)?;
+        loader.load_gltf(path)
+    }
+
+    fn load_rgba(&self, name: &str) -> Result<RgbaImage> {
+        let (loader, path) = self.resolve(name)?;
+        loader.load_rgba(path)
+    }
+
+    fn exists(&self, name: &str) -> bool {
+        self.resolve(name).is_ok()
+    }
+}
+
+impl fmt::Debug for Vfs {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mounts = self.mounts.read().unwrap();
+        let schemes: Vec<&str> = mounts.iter().map(|m| m.scheme.as_str()).collect();
+        f.debug_struct("Vfs").field("mounts", &schemes).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::{write_pack, PackLoader};
+
+    fn memory(files: &[(&str, &str)]) -> Box<dyn ResourceLoader> {
+        let loader = files
+            .iter()
+            .fold(MemoryLoader::new(), |loader, (name, text)| {
+                loader.file(name, *text)
+            });
+        Box::new(loader)
+    }
+
+    #[test]
+    fn memory_loader_loads_files() {
+        let loader = MemoryLoader::new().file("scripts/main.wren", "var x = 1");
+        assert_eq!(loader.load_text("scripts/main.wren").unwrap(), "var x = 1");
+        assert_eq!(
+            loader.load_bytes("./scripts/main.wren").unwrap(),
+            b"var x = 1"
+        );
+        assert!(loader.exists("scripts/../scripts/main.wren"));
+        assert!(!loader.exists("main.wren"));
+        assert!(loader.load_text("main.wren").is_err());
+
+        loader.insert("scripts/main.wren", "var x = 2");
+        assert_eq!(loader.load_text("scripts/main.wren").unwrap(), "var x = 2");
+        assert!(loader.remove("scripts/main.wren"));
+        assert!(!loader.exists("scripts/main.wren"));
+    }
+
+    #[test]
+    fn names_without_scheme_are_project_files() {
+        let vfs = Vfs::new()
+            .mount(ENGINE, memory(&[("a.glsl", "engine")]))
+            .mount(PROJECT, memory(&[("a.glsl", "project")]));
+        assert_eq!(vfs.load_text("a.glsl").unwrap(), "project");
+        assert_eq!(vfs.load_text("project://a.glsl").unwrap(), "project");
+        assert_eq!(vfs.load_text("engine://a.glsl").unwrap(), "engine");
+    }
+
+    #[test]
+    fn later_mounts_override_earlier_ones() {
+        let vfs = Vfs::new()
+            .mount(PROJECT, memory(&[("a.txt", "base"), ("b.txt", "base")]))
+            .mount(PROJECT, memory(&[("a.txt", "patch")]));
+        assert_eq!(vfs.load_text("a.txt").unwrap(), "patch");
+        // files missing from a later mount are found in earlier ones
+        assert_eq!(vfs.load_text("b.txt").unwrap(), "base");
+    }
+
+    #[test]
+    fn missing_files_and_mounts_are_errors() {
+        let vfs = Vfs::new().mount(PROJECT, memory(&[("a.txt", "a")]));
+        assert!(!vfs.exists("b.txt"));
+        assert!(vfs.load_text("b.txt").is_err());
+        assert!(!vfs.exists("engine://a.txt"));
+        let error = format!("{}", vfs.load_text("engine://a.txt").unwrap_err());
+        assert!(error.contains("engine://"), "{}", error);
+    }
+
+    #[test]
+    fn engine_files_are_built_in() {
+        let vfs = Vfs::new().mount(ENGINE, Box::new(MemoryLoader::engine()));
+        let blank = vfs.load_rgba("engine://textures/blank.png").unwrap();
+        assert!(blank.width() > 0 && blank.height() > 0);
+    }
+
+    #[test]
+    fn mounts_are_added_while_loading() {
+        let vfs = Vfs::new().mount(PROJECT, memory(&[("a.txt", "base")]));
+        assert!(!vfs.exists("mods://b.txt"));
+        vfs.add_mount(PROJECT, memory(&[("a.txt", "patch")]));
+        vfs.add_mount("mods", memory(&[("b.txt", "mod")]));
+        assert_eq!(vfs.load_text("a.txt").unwrap(), "patch");
+        assert_eq!(vfs.load_text("mods://b.txt").unwrap(), "mod");
+    }
+
+    #[test]
+    fn obj_materials_are_loaded_from_memory() {
+        let obj = "mtllib cube.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl red\nf 1 2 3\n";
+        let mtl = "newmtl red\nKd 1 0 0\n";
+        let vfs = Vfs::new().mount(
+            PROJECT,
+            memory(&[("models/cube.obj", obj), ("models/cube.mtl", mtl)]),
+        );
+        let (models, materials) = vfs.load_obj("models/cube.obj").unwrap();
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].mesh.indices.len(), 3);
+        assert_eq!(materials.len(), 1);
+        assert_eq!(materials[0].name, "red");
+    }
+
+    #[test]
+    fn packs_mount_over_project_files() {
+        let root = std::env::temp_dir().join(format!("lucien-pack-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("scripts")).unwrap();
+        std::fs::write(root.join("scripts/main.wren"), "packed").unwrap();
+        std::fs::write(root.join(".hidden"), "hidden").unwrap();
+        let out = std::env::temp_dir().join(format!("lucien-{}.lpak", std::process::id()));
+        let stats = write_pack(&root, &out, true).unwrap();
+        assert_eq!(stats.files, 1);
+
+        let pack = PackLoader::open(&out).unwrap();
+        assert!(!pack.exists(".hidden"));
+        let vfs = Vfs::new()
+            .mount(PROJECT, memory(&[("scripts/main.wren", "loose")]))
+            .mount(PROJECT, Box::new(pack));
+        assert_eq!(vfs.load_text("scripts/main.wren").unwrap(), "packed");
+
+        std::fs::remove_dir_all(&root).unwrap();
+        std::fs::remove_file(&out).unwrap();
+    }
+}
@@ -0,0 +1,238 @@
+use std::time::Duration;
+
+// Rates of the game loop
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoopConfig {
+    // target rendered frames per second
+    pub frame_rate: u32,
+    // fixed updates per second, independent of frame rate
+    pub update_rate: u32,
+    // most fixed updates run in one frame to catch up, time beyond
+    // that is skipped so a slow frame doesn't snowball
+    pub max_catch_up: u32,
+}
+
+// What to do in a frame
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Frame {
+    // false if the frame isn't due yet, do nothing
+    pub render: bool,
+    // fixed updates to run before render
+    pub updates: u32,
+    // frames missed since last frame because it took too long
+    pub dropped: u64,
+    // time left over after the fixed updates, in steps, in 0..1
+    pub alpha: f64,
+}
+
+// Counters since the loop started
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LoopStats {
+    pub frames: u64,
+    pub dropped_frames: u64,
+    pub updates: u64,
+    // fixed updates not run because of the catch up cap
+    pub skipped_updates: u64,
+}
+
+// Decides when a frame is due and how many fixed updates it runs,
+// from the time it's given, so it doesn't depend on the event loop.
+// Frames are due on a fixed grid, a late frame doesn't delay the
+// frames after it; frames that are missed entirely are dropped.
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    config: LoopConfig,
+    next_frame: Option<Duration>,
+    last: Option<Duration>,
+    accumulator: Duration,
+    stats: LoopStats,
+}
+
+impl LoopConfig {
+    pub fn frame_time(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.frame_rate.max(1) as f64)
+    }
+
+    pub fn update_step(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / self.update_rate.max(1) as f64)
+    }
+}
+
+impl Default for LoopConfig {
+    fn default() -> Self {
+        Self {
+            frame_rate: 60,
+            update_rate: 60,
+            max_catch_up: 5,
+        }
+    }
+}
+
+impl Scheduler {
+    pub fn new(config: LoopConfig) -> Self {
+        Self {
+            config,
+            next_frame: None,
+            last: None,
+            accumulator: Duration::default(),
+            stats: LoopStats::default(),
+        }
+    }
+
+    pub fn config(&self) -> &LoopConfig {
+        &self.config
+    }
+
+    pub fn stats(&self) -> LoopStats {
+        self.stats
+    }
+
+    // time to wait from `now` until next frame is due
+    pub fn wait_time(&self, now: Duration) -> Duration {
+        self.next_frame
+            .and_then(|next| next.checked_sub(now))
+            .unwrap_or_default()
+    }
+
+    // start a frame at `now`, the first frame is due immediately;
+    // a frame up to half a frame early is run, so a tick that wakes
+    // up slightly before the deadline isn't lost
+    pub fn frame(&mut self, now: Duration) -> Frame {
+        let frame_time = self.config.frame_time();
+        let next = self.next_frame.unwrap_or(now);
+        if now + frame_time / 2 < next {
+            return Frame::default();
+        }
+
+        // whole frames between the due time and now are missed
+        let late = now.checked_sub(next).unwrap_or_default();
+        let dropped = (late.as_nanos() / frame_time.as_nanos()) as u64;
+        self.next_frame = Some(next + frame_time * (dropped as u32 + 1));
+
+        if let Some(last) = self.last {
+            self.accumulator += now.checked_sub(last).unwrap_or_default();
+        }
+        self.last = Some(now);
+        let step = self.config.update_step();
+        let due = (self.accumulator.as_nanos() / step.as_nanos()) as u32;
+        self.accumulator -= step * due;
+        let alpha = self.accumulator.as_secs_f64() / step.as_secs_f64();
+        let updates = due.min(self.config.max_catch_up);
+
+        self.stats.frames += 1;
+        self.stats.dropped_frames += dropped;
+        self.stats.updates += updates as u64;
+        self.stats.skipped_updates += (due - updates) as u64;
+
+        Frame {
+            render: true,
+            updates,
+            dropped,
+            alpha,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    fn config(frame_rate: u32, update_rate: u32, max_catch_up: u32) -> LoopConfig {
+        LoopConfig {
+            frame_rate,
+            update_rate,
+            max_catch_up,
+        }
+    }
+
+    #[test]
+    fn first_frame_is_due_immediately() {
+        let mut scheduler = Scheduler::new(LoopConfig::default());
+        assert_eq!(scheduler.wait_time(ms(0)), ms(0));
+        let frame = scheduler.frame(ms(0));
+        assert!(frame.render);
+        assert_eq!(frame.updates, 0);
+        assert_eq!(frame.dropped, 0);
+    }
+
+    #[test]
+    fn early_frame_is_not_run() {
+        let mut scheduler = Scheduler::new(config(10, 10, 5));
+        scheduler.frame(ms(0));
+        assert_eq!(scheduler.wait_time(ms(20)), ms(80));
+        assert!(!scheduler.frame(ms(20)).render);
+        // slightly early is fine
+        assert!(scheduler.frame(ms(99)).render);
+        assert_eq!(scheduler.stats().frames, 2);
+    }
+
+    #[test]
+    fn frames_stay_on_grid() {
+        let mut scheduler = Scheduler::new(config(10, 10, 5));
+        scheduler.frame(ms(0));
+        // a frame 30ms late doesn't delay the next one
+        scheduler.frame(ms(130));
+        assert_eq!(scheduler.wait_time(ms(130)), ms(70));
+    }
+
+    #[test]
+    fn update_rate_is_independent_of_frame_rate() {
+        // 1 second at 30 fps and at 120 fps runs the same fixed updates
+        for fps in [30, 120].iter() {
+            let mut scheduler = Scheduler::new(config(*fps, 50, 5));
+            let frame_time = scheduler.config().frame_time();
+            let mut now = Duration::default();
+            let mut updates = 0;
+            for _ in 0..=*fps {
+                updates += scheduler.frame(now).updates;
+                now += frame_time;
+            }
+            assert!(
+                (49..=50).contains(&updates),
+                "{} updates at {} fps",
+                updates,
+                fps
+            );
+        }
+    }
+
+    #[test]
+    fn missed_frames_are_dropped() {
+        let mut scheduler = Scheduler::new(config(10, 10, 100));
+        scheduler.frame(ms(0));
+        // the frame due at 100 runs late, the ones at 200, 300 and 400 are missed
+        let frame = scheduler.frame(ms(420));
+        assert!(frame.render);
+        assert_eq!(frame.dropped, 3);
+        assert_eq!(scheduler.wait_time(ms(420)), ms(80));
+        // updates still catch up
+        assert_eq!(frame.updates, 4);
+        assert_eq!(scheduler.stats().dropped_frames, 3);
+    }
+
+    #[test]
+    fn catch_up_is_capped() {
+        let mut scheduler = Scheduler::new(config(10, 20, 5));
+        scheduler.frame(ms(0));
+        let frame = scheduler.frame(ms(1000));
+        assert_eq!(frame.updates, 5);
+        let stats = scheduler.stats();
+        assert_eq!(stats.updates, 5);
+        assert_eq!(stats.skipped_updates, 15);
+        // skipped time is gone, next frame runs a normal amount
+        assert_eq!(scheduler.frame(ms(1100)).updates, 2);
+    }
+
+    #[test]
+    fn alpha_is_leftover_of_step() {
+        let mut scheduler = Scheduler::new(config(4, 10, 5));
+        scheduler.frame(ms(0));
+        let frame = scheduler.frame(ms(250));
+        assert_eq!(frame.updates, 2);
+        assert!((frame.alpha - 0.5).abs() < 1e-6);
+    }
+}
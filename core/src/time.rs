@@ -0,0 +1,191 @@
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+// Source of time, so game time can be driven by a fake clock in tests
+// or by frame count in offscreen renders.
+pub trait Clock {
+    // time since the clock started
+    fn now(&self) -> Duration;
+}
+
+// Wall clock time
+#[derive(Debug, Clone, Copy)]
+pub struct SystemClock {
+    start: Instant,
+}
+
+// Clock that only moves when told to
+#[derive(Debug, Default)]
+pub struct ManualClock {
+    now: Cell<Duration>,
+}
+
+// Frame timing passed to scripts. Fixed steps of a frame are decided
+// by the loop scheduler, which owns the accumulated time, so they run
+// at the same rate whatever the frame rate is. Times are in seconds.
+#[derive(Debug, Clone)]
+pub struct Time {
+    // seconds since first tick
+    pub elapsed: f64,
+    // seconds since last tick
+    pub delta: f64,
+    // number of ticks before this one
+    pub frame: u64,
+    pub fixed_step: f64,
+    // fixed steps of this frame not consumed yet
+    updates: u32,
+    alpha: f64,
+    last: Option<Duration>,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        SystemClock::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, dt: Duration) {
+        self.now.set(self.now.get() + dt);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+}
+
+impl Time {
+    pub fn new(fixed_step: f64) -> Self {
+        Self {
+            elapsed: 0.0,
+            delta: 0.0,
+            frame: 0,
+            fixed_step,
+            updates: 0,
+            alpha: 0.0,
+            last: None,
+        }
+    }
+
+    // start a new frame, the first frame has zero delta
+    pub fn tick(&mut self, clock: &dyn Clock) {
+        let now = clock.now();
+        self.delta = match self.last {
+            Some(last) => {
+                self.frame += 1;
+                now.checked_sub(last).unwrap_or_default().as_secs_f64()
+            }
+            None => 0.0,
+        };
+        self.last = Some(now);
+        self.elapsed += self.delta;
+    }
+
+    // fixed steps and alpha of the frame, from the scheduler
+    pub fn schedule(&mut self, updates: u32, alpha: f64) {
+        self.updates = updates;
+        self.alpha = alpha;
+    }
+
+    // returns true and consumes a step if there's a fixed step to run,
+    // call it in a loop until it returns false
+    pub fn fixed_update(&mut self) -> bool {
+        if self.updates > 0 {
+            self.updates -= 1;
+            return true;
+        }
+        false
+    }
+
+    // how far between two fixed steps, in 0..1, to interpolate states
+    pub fn alpha(&self) -> f64 {
+        self.alpha
+    }
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Time::new(1.0 / 60.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn first_tick_has_zero_delta() {
+        let clock = ManualClock::new();
+        clock.advance(ms(500));
+        let mut time = Time::default();
+        time.tick(&clock);
+        assert_eq!(time.delta, 0.0);
+        assert_eq!(time.elapsed, 0.0);
+        assert_eq!(time.frame, 0);
+    }
+
+    #[test]
+    fn delta_and_elapsed() {
+        let clock = ManualClock::new();
+        let mut time = Time::default();
+        time.tick(&clock);
+        clock.advance(ms(16));
+        time.tick(&clock);
+        clock.advance(ms(34));
+        time.tick(&clock);
+        assert!((time.delta - 0.034).abs() < 1e-9);
+        assert!((time.elapsed - 0.05).abs() < 1e-9);
+        assert_eq!(time.frame, 2);
+    }
+
+    #[test]
+    fn scheduled_steps_are_consumed() {
+        let mut time = Time::new(0.1);
+        time.schedule(2, 0.5);
+        assert!(time.fixed_update());
+        assert!(time.fixed_update());
+        assert!(!time.fixed_update());
+        assert!((time.alpha() - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn clock_going_back_is_ignored() {
+        struct Backwards(Cell<u64>);
+        impl Clock for Backwards {
+            fn now(&self) -> Duration {
+                let n = self.0.get();
+                self.0.set(n.saturating_sub(10));
+                ms(n)
+            }
+        }
+        let clock = Backwards(Cell::new(100));
+        let mut time = Time::default();
+        time.tick(&clock);
+        time.tick(&clock);
+        assert_eq!(time.delta, 0.0);
+    }
+}
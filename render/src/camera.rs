@@ -1,5 +1,6 @@
 use bytemuck::{Pod, Zeroable};
 use glam::{vec3, Mat4, Vec3};
+use lucien_core::resources::CameraDesc;
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
@@ -40,6 +41,17 @@ impl Camera {
         camera
     }
 
+    pub fn from_desc(desc: &CameraDesc, aspect_ratio: f32) -> Self {
+        let mut camera = Camera::new(Vec3::from(desc.eye), aspect_ratio);
+        camera.look_at = Vec3::from(desc.look_at);
+        camera.up = Vec3::from(desc.up);
+        camera.fov = desc.fov;
+        camera.z_near = desc.z_near;
+        camera.z_far = desc.z_far;
+        camera.update_view_matrix();
+        camera
+    }
+
     pub fn update_view_matrix(&mut self) -> Mat4 {
         let view = Mat4::look_at_rh(self.eye, self.look_at, self.up);
         let proj = Mat4::perspective_rh(self.fov, self.aspect_ratio, self.z_near, self.z_far);
@@ -0,0 +1,89 @@
+use crate::{Camera, Light, LightId, NodeId, Scene};
+use glam::Vec3;
+use lucien_core::ecs::Entity;
+use lucien_core::logger::logger;
+use slog::warn;
+use std::collections::HashMap;
+
+// Components of entities in a scene world, besides these `Transform`,
+// `Light` and `Camera` are components too. An entity's world matrix
+// is its transform relative to its scene node, or to world origin.
+
+// Draws the mesh of a loaded model with the entity's world matrix,
+// entities may draw the same model
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshRenderer {
+    // index of scene models
+    pub model: usize,
+}
+
+// Attaches an entity to a node of scene graph, so it moves with the node
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SceneNode(pub NodeId);
+
+// Name to find an entity by
+#[derive(Debug, Clone, PartialEq)]
+pub struct Name(pub String);
+
+// Copy the first entity with a camera to the scene camera, it looks
+// along -z of the entity. The scene camera is left alone if there's
+// no camera entity, e.g. when scripts move it directly.
+pub fn camera_system() -> impl FnMut(&mut Scene) + Send {
+    |scene: &mut Scene| {
+        let camera = scene.world.query::<Camera>().next();
+        let (entity, camera) = match camera {
+            Some((entity, camera)) => (entity, *camera),
+            None => return,
+        };
+        if let Some(world) = scene.world_matrix(entity) {
+            let eye = world.transform_point3(Vec3::ZERO);
+            scene.camera.eye = eye;
+            scene.camera.look_at = eye + world.transform_vector3(-Vec3::Z);
+            scene.camera.up = world.transform_vector3(Vec3::Y);
+            scene.camera.fov = camera.fov;
+            scene.camera.z_near = camera.z_near;
+            scene.camera.z_far = camera.z_far;
+        }
+    }
+}
+
+// Keep a scene light for every entity with a light; lights shine from
+// the entity position along its -z. Lights of despawned entities, or
+// entities whose light component is removed, are removed from scene.
+pub fn light_system() -> impl FnMut(&mut Scene) + Send {
+    let mut ids: HashMap<Entity, LightId> = HashMap::new();
+    move |scene: &mut Scene| {
+        let lights: Vec<(Entity, Light)> = scene
+            .world
+            .query::<Light>()
+            .filter_map(|(entity, light)| {
+                let world = scene.world_matrix(entity)?;
+                let mut light = *light;
+                light.place(
+                    world.transform_point3(Vec3::ZERO),
+                    world.transform_vector3(-Vec3::Z).normalize(),
+                );
+                Some((entity, light))
+            })
+            .collect();
+
+        ids.retain(|entity, id| {
+            let keep = lights.iter().any(|(e, _)| e == entity);
+            if !keep {
+                scene.lights.remove(*id);
+            }
+            keep
+        });
+        for (entity, light) in lights {
+            match ids.get(&entity).and_then(|id| scene.lights.get_mut(*id)) {
+                Some(scene_light) => *scene_light = light,
+                None => match scene.lights.add(light) {
+                    Some(id) => {
+                        ids.insert(entity, id);
+                    }
+                    None => warn!(logger(), "too many lights, {:?} is not lit", entity),
+                },
+            }
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{Material, Mesh, Model, NodeId, Scene, Transform, Vertex};
+use anyhow::{anyhow, Result};
+use glam::{Quat, Vec3};
+use image::RgbaImage;
+
+use lucien_core::logger::logger;
+use lucien_core::resources::{assets, MeshData, MeshDesc};
+use slog::warn;
+
+// Convert a glTF scene into scene nodes, models and materials.
+// Skins and animations are not supported yet, they are ignored.
+impl Scene {
+    // nodes in the default scene of the file are added under the node
+    pub(crate) fn load_gltf(
+        &mut self, desc: &MeshDesc, node: NodeId, device: &wgpu::Device, queue: &wgpu::Queue,
+    ) -> Result<()> {
+        let path = desc.path.as_str();
+        let mesh = assets().load::<MeshData>(path)?;
+        let (document, buffers, images) = match &*mesh {
+            MeshData::Gltf(gltf) => &**gltf,
+            MeshData::Obj(..) => return Err(anyhow!("{} is not a glTF file", path)),
+        };
+        if document.skins().next().is_some() || document.animations().next().is_some() {
+            warn!(logger(), "skins and animations in {} are ignored", path);
+        }
+
+        // material ids in the file are relative to the file,
+        // offset them by the materials we already have;
+        // the default material is the last one
+        let base = self.materials.len();
+        if let Some(material) = desc.material.as_ref() {
+            self.materials
+                .push(Material::from_desc(device, queue, path, material)?);
+        } else {
+            for material in document.materials() {
+                let diffuse = material
+                    .pbr_metallic_roughness()
+                    .base_color_texture()
+                    .and_then(|info| images.get(info.texture().source().index()))
+                    .and_then(to_rgba);
+                self.materials.push(Material::from_gltf(
+                    device,
+                    queue,
+                    &material,
+                    diffuse.as_ref(),
+                )?);
+            }
+            self.materials.push(Material::default(device, queue)?);
+        }
+        let n_materials = self.materials.len() - base;
+
+        let scene = document
+            .default_scene()
+            .or_else(|| document.scenes().next())
+            .ok_or_else(|| anyhow!("no scene in gltf file {}", path))?;
+        let mut stack: Vec<(gltf::Node, NodeId)> =
+            scene.nodes().map(|child| (child, node)).collect();
+        while let Some((gltf_node, parent)) = stack.pop() {
+            let (translation, rotation, scale) = gltf_node.transform().decomposed();
+            let transform = Transform::new(
+                Vec3::from(translation),
+                Quat::from_xyzw(rotation[0], rotation[1], rotation[2], rotation[3]),
+                Vec3::from(scale),
+            );
+            let name = gltf_node
+                .name()
+                .map(|name| name.to_string())
+                .unwrap_or_else(|| format!("{} node {}", path, gltf_node.index()));
+            let id = self.nodes.add(&name, transform, Some(parent));
+
+            if let Some(mesh) = gltf_node.mesh() {
+                for primitive in mesh.primitives() {
+                    if primitive.mode() != gltf::mesh::Mode::Triangles {
+                        warn!(logger(), "only triangles are supported, skip {}", name);
+                        continue;
+                    }
+                    // the last material is default
+                    let material = match desc.material {
+                        Some(_) => base,
+                        None => primitive
+                            .material()
+                            .index()
+                            .map(|index| base + index)
+                            .unwrap_or(base + n_materials - 1),
+                    };
+                    let reader = primitive
+                        .reader(|buffer| buffers.get(buffer.index()).map(|data| &data.0[..]));
+                    let positions: Vec<[f32; 3]> = match reader.read_positions() {
+                        Some(positions) => positions.collect(),
+                        None => continue,
+                    };
+                    let mut normals = reader.read_normals();
+                    let mut tex_coords = reader.read_tex_coords(0).map(|uv| uv.into_f32());
+                    let mut tangents = reader.read_tangents();
+                    let has_normals = normals.is_some();
+                    let has_tangents = tangents.is_some();
+                    let mut vertices: Vec<Vertex> = positions
+                        .iter()
+                        .map(|position| Vertex {
+                            position: *position,
+                            normal: normals
+                                .as_mut()
+                                .and_then(|normals| normals.next())
+                                .unwrap_or([0.0, 0.0, 0.0]),
+                            tex_coord: tex_coords
+                                .as_mut()
+                                .and_then(|uv| uv.next())
+                                .unwrap_or([0.0, 0.0]),
+                            tangent: tangents
+                                .as_mut()
+                                .and_then(|tangents| tangents.next())
+                                .unwrap_or([0.0, 0.0, 0.0, 0.0]),
+                        })
+                        .collect();
+                    let mut indices: Vec<u32> = match reader.read_indices() {
+                        Some(indices) => indices.into_u32().collect(),
+                        None => (0..vertices.len() as u32).collect(),
+                    };
+                    if !has_normals {
+                        let (v, i) = Vertex::compute_normals(vertices, indices, desc.normals);
+                        vertices = v;
+                        indices = i;
+                    }
+                    if !has_normals || !has_tangents {
+                        Vertex::compute_tangents(&mut vertices, &indices);
+                    }
+                    let mesh = Mesh::from_vertices(device, &vertices, &indices, material, &name);
+                    self.models.push(Model {
+                        mesh,
+                        name: name.clone(),
+                        node: id,
+                    });
+                }
+            }
+            stack.extend(gltf_node.children().map(|child| (child, id)));
+        }
+
+        Ok(())
+    }
+}
+
+// images are decoded as 8 bits per channel by gltf
+fn to_rgba(image: &gltf::image::Data) -> Option<RgbaImage> {
+    use gltf::image::Format;
+
+    let pixels = &image.pixels;
+    let rgba: Vec<u8> = match image.format {
+        Format::R8G8B8A8 => pixels.clone(),
+        Format::R8G8B8 => pixels
+            .chunks(3)
+            .flat_map(|p| vec![p[0], p[1], p[2], 255])
+            .collect(),
+        Format::B8G8R8A8 => pixels
+            .chunks(4)
+            .flat_map(|p| vec![p[2], p[1], p[0], p[3]])
+            .collect(),
+        Format::B8G8R8 => pixels
+            .chunks(3)
+            .flat_map(|p| vec![p[2], p[1], p[0], 255])
+            .collect(),
+        Format::R8G8 => pixels
+            .chunks(2)
+            .flat_map(|p| vec![p[0], p[1], 0, 255])
+            .collect(),
+        Format::R8 => pixels.iter().flat_map(|p| vec![*p, *p, *p, 255]).collect(),
+        format => {
+            warn!(logger(), "unsupported texture format {:?}", format);
+            return None;
+        }
+    };
+    RgbaImage::from_raw(image.width, image.height, rgba)
+}
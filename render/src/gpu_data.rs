@@ -4,14 +4,34 @@ pub use crevice::std140::{AsStd140, Std140};
 // the problem is, if we just cast to [u8], gpu doesn't like that,
 // it needs a padding. So we could utilize a library.
 
-use glam::Vec3;
+use crate::{Light, MAX_LIGHTS};
+use glam::{Mat4, Vec3};
+use lucien_core::resources::MaterialDesc;
 use mint::Vector3;
 
-#[derive(AsStd140)]
-pub struct PointLightRaw {
-    pub position: Vector3<f32>,
-    pub color: Vector3<f32>,
+// One light in the lights array, the layout matches std140 so
+// every vec3 is followed by a scalar to fill the 16 bytes slot.
+// `kind` is 0 for point, 1 for directional, 2 for spot light,
+// cutoffs are stored as cosine of the angles.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+pub struct LightRaw {
+    pub position: [f32; 3],
+    pub kind: u32,
+    pub direction: [f32; 3],
     pub intensity: f32,
+    pub color: [f32; 3],
+    pub cutoff: f32,
+    pub outer_cutoff: f32,
+    _p0: [f32; 3],
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct LightsRaw {
+    pub count: u32,
+    _p0: [u32; 3],
+    pub lights: [LightRaw; MAX_LIGHTS],
 }
 
 #[derive(AsStd140)]
@@ -42,12 +62,51 @@ pub struct UniformsRaw {
     _p3: [f32; 3],
 }
 
-impl PointLightRaw {
-    pub fn from_vec3(position: &Vec3, color: &Vec3, intensity: f32) -> Self {
+// per instance model matrix
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct InstanceRaw {
+    pub model: [[f32; 4]; 4],
+}
+
+impl LightRaw {
+    pub fn from(light: &Light) -> Self {
+        let (kind, cutoff, outer_cutoff) = match light {
+            Light::Point(_) => (0, 0.0, 0.0),
+            Light::Directional(_) => (1, 0.0, 0.0),
+            Light::Spot(spot) => (2, spot.cutoff.cos(), spot.outer_cutoff.cos()),
+        };
         Self {
-            position: vec3_to_raw(position),
-            color: vec3_to_raw(color),
-            intensity,
+            position: light.position().into(),
+            kind,
+            direction: light.direction().into(),
+            intensity: light.intensity(),
+            color: light.color().into(),
+            cutoff,
+            outer_cutoff,
+            _p0: [0.0, 0.0, 0.0],
+        }
+    }
+}
+
+impl LightsRaw {
+    // lights beyond MAX_LIGHTS are ignored
+    pub fn from<'a, I: Iterator<Item = &'a Light>>(lights: I) -> Self {
+        let mut raw = Self::default();
+        for (i, light) in lights.take(MAX_LIGHTS).enumerate() {
+            raw.lights[i] = LightRaw::from(light);
+            raw.count = i as u32 + 1;
+        }
+        raw
+    }
+}
+
+impl Default for LightsRaw {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            _p0: [0, 0, 0],
+            lights: [LightRaw::default(); MAX_LIGHTS],
         }
     }
 }
@@ -71,6 +130,29 @@ impl MaterialRaw {
             shininess: material.shininess,
         }
     }
+
+    pub fn from_desc(desc: &MaterialDesc) -> Self {
+        Self {
+            ambient: Vector3::from_slice(&desc.ambient),
+            diffuse: Vector3::from_slice(&desc.diffuse),
+            specular: Vector3::from_slice(&desc.specular),
+            shininess: desc.shininess,
+        }
+    }
+
+    // approximate pbr with phong, rougher surface has dimmer, wider highlight
+    pub fn from_gltf(material: &gltf::Material) -> Self {
+        let pbr = material.pbr_metallic_roughness();
+        let [r, g, b, _] = pbr.base_color_factor();
+        let smoothness = 1.0 - pbr.roughness_factor();
+        let specular = smoothness * 0.5;
+        Self {
+            ambient: Vector3::from_slice(&[r, g, b]),
+            diffuse: Vector3::from_slice(&[r, g, b]),
+            specular: Vector3::from_slice(&[specular, specular, specular]),
+            shininess: smoothness * smoothness * 128.0,
+        }
+    }
 }
 
 impl Default for MaterialRaw {
@@ -86,6 +168,18 @@ impl Default for MaterialRaw {
 
 unsafe impl bytemuck::Pod for UniformsRaw {}
 unsafe impl bytemuck::Zeroable for UniformsRaw {}
+unsafe impl bytemuck::Pod for LightsRaw {}
+unsafe impl bytemuck::Zeroable for LightsRaw {}
+unsafe impl bytemuck::Pod for InstanceRaw {}
+unsafe impl bytemuck::Zeroable for InstanceRaw {}
+
+impl InstanceRaw {
+    pub fn from(model: &Mat4) -> Self {
+        Self {
+            model: model.to_cols_array_2d(),
+        }
+    }
+}
 
 impl UniformsRaw {
     pub fn from(scene: &super::Scene) -> Self {
@@ -0,0 +1,85 @@
+use crate::gpu_data::InstanceRaw;
+use glam::Mat4;
+use wgpu::util::DeviceExt;
+
+const VEC4_SIZE: wgpu::BufferAddress = std::mem::size_of::<[f32; 4]>() as wgpu::BufferAddress;
+
+// Model matrices of every entity drawn in a scene, sent as per-instance
+// vertex data. Entry `i` of scene draw list is drawn as instance `i`,
+// so it reads the i-th matrix.
+#[derive(Debug)]
+pub struct Instances {
+    pub buffer: wgpu::Buffer,
+    capacity: usize,
+}
+
+impl Instances {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let capacity = 16;
+        let buffer = Instances::create_buffer(capacity, device);
+
+        Self { buffer, capacity }
+    }
+
+    fn create_buffer(capacity: usize, device: &wgpu::Device) -> wgpu::Buffer {
+        let size = (capacity * std::mem::size_of::<InstanceRaw>()) as wgpu::BufferAddress;
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Instances Buffer"),
+            size,
+            usage: wgpu::BufferUsage::VERTEX | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    // copy world matrices to the instance buffer,
+    // the buffer grows if there are more matrices than it can hold
+    pub fn update_buffer(
+        &mut self, matrices: &[Mat4], encoder: &mut wgpu::CommandEncoder, device: &wgpu::Device,
+    ) {
+        if matrices.is_empty() {
+            return;
+        }
+        if matrices.len() > self.capacity {
+            self.capacity = matrices.len().next_power_of_two();
+            self.buffer = Instances::create_buffer(self.capacity, device);
+        }
+        let raw: Vec<InstanceRaw> = matrices.iter().map(InstanceRaw::from).collect();
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Instances Buffer"),
+            contents: bytemuck::cast_slice(&raw),
+            usage: wgpu::BufferUsage::COPY_SRC,
+        });
+        let buffer_size = (raw.len() * std::mem::size_of::<InstanceRaw>()) as wgpu::BufferAddress;
+        encoder.copy_buffer_to_buffer(&buffer, 0, &self.buffer, 0, buffer_size);
+    }
+
+    // a mat4 takes 4 vec4 locations, after vertex attributes
+    pub fn desc<'a>() -> wgpu::VertexBufferDescriptor<'a> {
+        wgpu::VertexBufferDescriptor {
+            stride: std::mem::size_of::<InstanceRaw>() as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttributeDescriptor {
+                    offset: 0,
+                    shader_location: 8,
+                    format: wgpu::VertexFormat::Float4,
+                },
+                wgpu::VertexAttributeDescriptor {
+                    offset: VEC4_SIZE,
+                    shader_location: 9,
+                    format: wgpu::VertexFormat::Float4,
+                },
+                wgpu::VertexAttributeDescriptor {
+                    offset: VEC4_SIZE * 2,
+                    shader_location: 10,
+                    format: wgpu::VertexFormat::Float4,
+                },
+                wgpu::VertexAttributeDescriptor {
+                    offset: VEC4_SIZE * 3,
+                    shader_location: 11,
+                    format: wgpu::VertexFormat::Float4,
+                },
+            ],
+        }
+    }
+}
@@ -1,18 +1,25 @@
 mod camera;
+mod components;
 mod depth_texture;
+mod gltf_scene;
+mod instance;
 mod light;
 mod material;
 mod mesh;
+mod node;
 mod render_texture;
 mod scene;
 mod texture;
 mod vertex;
 
 pub use camera::*;
+pub use components::*;
 pub use depth_texture::*;
+pub use instance::*;
 pub use light::*;
 pub use material::*;
 pub use mesh::*;
+pub use node::*;
 pub use render_texture::*;
 pub use scene::*;
 pub use texture::*;
@@ -22,6 +29,7 @@ pub use vertex::*;
 pub mod buffer;
 mod pipelines;
 mod renderer;
+pub mod shader;
 mod uniforms;
 use pipelines::*;
 pub use renderer::*;
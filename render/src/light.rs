@@ -1,15 +1,56 @@
 use glam::{vec3, Vec3};
+use lucien_core::resources::{AmbientLightDesc, LightDesc};
 
 use crate::buffer::uniform_buffer;
 use crate::gpu_data::*;
 
+// Must match MAX_LIGHTS in shaders
+pub const MAX_LIGHTS: usize = 16;
+
+pub type LightId = usize;
+
 // Point Light
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct PointLight {
     pub position: Vec3,
     // set a bound?
     pub color: Vec3,
     pub intensity: f32,
+}
+
+// Directional light, e.g. the sun, has no position
+#[derive(Debug, Clone, Copy)]
+pub struct DirectionalLight {
+    pub direction: Vec3,
+    pub color: Vec3,
+    pub intensity: f32,
+}
+
+// Spot light, lits a cone from position towards direction;
+// angles are in radians, light fades out between cutoff and outer cutoff
+#[derive(Debug, Clone, Copy)]
+pub struct SpotLight {
+    pub position: Vec3,
+    pub direction: Vec3,
+    pub color: Vec3,
+    pub intensity: f32,
+    pub cutoff: f32,
+    pub outer_cutoff: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Light {
+    Point(PointLight),
+    Directional(DirectionalLight),
+    Spot(SpotLight),
+}
+
+// All lights in a scene, packed into one uniform array with a count.
+// Lights are referred by id, so removing a light doesn't invalidate others.
+#[derive(Debug)]
+pub struct Lights {
+    lights: Vec<(LightId, Light)>,
+    next_id: LightId,
     pub buffer: wgpu::Buffer,
     pub bind_group_layout: wgpu::BindGroupLayout,
     pub bind_group: wgpu::BindGroup,
@@ -24,42 +65,213 @@ pub struct AmbientLight {
 }
 
 impl PointLight {
-    pub fn new(position: Vec3, color: Vec3, device: &wgpu::Device) -> Self {
+    pub fn new(position: Vec3, color: Vec3) -> Self {
         let intensity = 1.0;
-        let raw = PointLightRaw::from_vec3(&position, &color, intensity);
-        let buffer = uniform_buffer(
-            raw.as_std140().as_bytes(),
-            device,
-            Some("Point Light Buffer"),
-        );
-        let (bind_group_layout, bind_group) = PointLightExt::layout(&buffer, device);
-
-        PointLight {
+
+        Self {
             position,
+            color,
+            intensity,
+        }
+    }
+}
+
+impl Default for PointLight {
+    fn default() -> Self {
+        let position = vec3(0.7, 0.0, 2.0);
+        let color = vec3(0.1, 0.1, 0.1);
+        PointLight::new(position, color)
+    }
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vec3, color: Vec3) -> Self {
+        let intensity = 1.0;
+
+        Self {
+            direction,
+            color,
             intensity,
+        }
+    }
+}
+
+impl SpotLight {
+    // angle is the cone half angle in radians
+    pub fn new(position: Vec3, direction: Vec3, color: Vec3, angle: f32) -> Self {
+        let intensity = 1.0;
+        // soften the edge a little
+        let outer_cutoff = angle * 1.1;
+
+        Self {
+            position,
+            direction,
             color,
+            intensity,
+            cutoff: angle,
+            outer_cutoff,
+        }
+    }
+}
+
+impl Light {
+    pub fn from_desc(desc: &LightDesc) -> Self {
+        match *desc {
+            LightDesc::Point {
+                position,
+                color,
+                intensity,
+            } => Light::Point(PointLight {
+                position: position.into(),
+                color: color.into(),
+                intensity,
+            }),
+            LightDesc::Directional {
+                direction,
+                color,
+                intensity,
+            } => Light::Directional(DirectionalLight {
+                direction: direction.into(),
+                color: color.into(),
+                intensity,
+            }),
+            LightDesc::Spot {
+                position,
+                direction,
+                color,
+                intensity,
+                angle,
+            } => {
+                let mut light =
+                    SpotLight::new(position.into(), direction.into(), color.into(), angle);
+                light.intensity = intensity;
+                Light::Spot(light)
+            }
+        }
+    }
+
+    // directional lights has no position
+    pub fn position(&self) -> Vec3 {
+        match self {
+            Light::Point(light) => light.position,
+            Light::Directional(_) => Vec3::ZERO,
+            Light::Spot(light) => light.position,
+        }
+    }
+
+    // point lights has no direction
+    pub fn direction(&self) -> Vec3 {
+        match self {
+            Light::Point(_) => Vec3::ZERO,
+            Light::Directional(light) => light.direction,
+            Light::Spot(light) => light.direction,
+        }
+    }
+
+    pub fn color(&self) -> Vec3 {
+        match self {
+            Light::Point(light) => light.color,
+            Light::Directional(light) => light.color,
+            Light::Spot(light) => light.color,
+        }
+    }
+
+    pub fn intensity(&self) -> f32 {
+        match self {
+            Light::Point(light) => light.intensity,
+            Light::Directional(light) => light.intensity,
+            Light::Spot(light) => light.intensity,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Light::Point(_) => "point",
+            Light::Directional(_) => "directional",
+            Light::Spot(_) => "spot",
+        }
+    }
+
+    // move the light, each kind takes what it has
+    pub fn place(&mut self, position: Vec3, direction: Vec3) {
+        match self {
+            Light::Point(light) => light.position = position,
+            Light::Directional(light) => light.direction = direction,
+            Light::Spot(light) => {
+                light.position = position;
+                light.direction = direction;
+            }
+        }
+    }
+}
+
+impl Lights {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let raw = LightsRaw::default();
+        let buffer = uniform_buffer(bytemuck::cast_slice(&[raw]), device, Some("Lights Buffer"));
+        let (bind_group_layout, bind_group) = LightsExt::layout(&buffer, device);
+
+        Self {
+            lights: vec![],
+            next_id: 0,
             buffer,
             bind_group_layout,
             bind_group,
         }
     }
 
-    pub fn default(device: &wgpu::Device) -> Self {
-        let position = vec3(0.7, 0.0, 2.0);
-        let color = vec3(0.1, 0.1, 0.1);
-        PointLight::new(position, color, device)
+    // add a light to scene, returns None if there are already MAX_LIGHTS
+    pub fn add(&mut self, light: Light) -> Option<LightId> {
+        if self.lights.len() >= MAX_LIGHTS {
+            return None;
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        self.lights.push((id, light));
+        Some(id)
+    }
+
+    pub fn remove(&mut self, id: LightId) -> Option<Light> {
+        let index = self.lights.iter().position(|(i, _)| *i == id)?;
+        Some(self.lights.remove(index).1)
+    }
+
+    pub fn get(&self, id: LightId) -> Option<&Light> {
+        self.lights
+            .iter()
+            .find(|(i, _)| *i == id)
+            .map(|(_, light)| light)
+    }
+
+    pub fn get_mut(&mut self, id: LightId) -> Option<&mut Light> {
+        self.lights
+            .iter_mut()
+            .find(|(i, _)| *i == id)
+            .map(|(_, light)| light)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Light> {
+        self.lights.iter().map(|(_, light)| light)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Light> {
+        self.lights.iter_mut().map(|(_, light)| light)
+    }
+
+    pub fn len(&self) -> usize {
+        self.lights.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
     }
 
     // create a buffer contains latest data, that we need to use a buffer to send data
-    // copy the buffer to previously created light buffer
+    // copy the buffer to previously created lights buffer
     pub fn update_buffer(&self, encoder: &mut wgpu::CommandEncoder, device: &wgpu::Device) {
-        let raw = PointLightRaw::from_vec3(&self.position, &self.color, self.intensity);
-        let buffer = uniform_buffer(
-            raw.as_std140().as_bytes(),
-            device,
-            Some("Point Light Buffer"),
-        );
-        let buffer_size = std::mem::size_of::<PointLightRaw>() as wgpu::BufferAddress;
+        let raw = LightsRaw::from(self.iter());
+        let buffer = uniform_buffer(bytemuck::cast_slice(&[raw]), device, Some("Lights Buffer"));
+        let buffer_size = std::mem::size_of::<LightsRaw>() as wgpu::BufferAddress;
         encoder.copy_buffer_to_buffer(&buffer, 0, &self.buffer, 0, buffer_size);
     }
 }
@@ -74,10 +286,17 @@ impl AmbientLight {
     pub fn default() -> Self {
         AmbientLight::new(vec3(1.0, 1.0, 1.0))
     }
+
+    pub fn from_desc(desc: &AmbientLightDesc) -> Self {
+        Self {
+            color: desc.color.into(),
+            intensity: desc.intensity,
+        }
+    }
 }
 
-struct PointLightExt;
-impl PointLightExt {
+struct LightsExt;
+impl LightsExt {
     pub fn layout(
         buffer: &wgpu::Buffer, device: &wgpu::Device,
     ) -> (wgpu::BindGroupLayout, wgpu::BindGroup) {
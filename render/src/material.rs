@@ -1,9 +1,16 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use image::RgbaImage;
 use std::path::Path;
 
 use crate::buffer::uniform_buffer;
 use crate::gpu_data::*;
 use crate::Texture;
+use lucien_core::logger::logger;
+use lucien_core::resources::{assets, Handle, MaterialDesc, TextureData};
+use slog::warn;
+
+// used when a material has no texture, or the texture is missing
+static BLANK_TEXTURE: &str = "engine://textures/blank.png";
 
 #[derive(Debug)]
 pub struct Material {
@@ -17,37 +24,60 @@ pub struct Material {
 struct MaterialExt;
 
 impl Material {
+    // texture in material is relative to the obj file at `path`
     pub fn new(
-        device: &wgpu::Device, queue: &wgpu::Queue, material: &tobj::Material,
+        device: &wgpu::Device, queue: &wgpu::Queue, material: &tobj::Material, path: &str,
     ) -> Result<Self> {
-        // let path = format!("data/{}", material.diffuse_texture);
-        let abs = Path::new(".")
-            .join("render/src/textures/blank.png")
-            .canonicalize()?;
-        let rgba = &image::open(abs)?.to_rgba8();
-        let diffuse_texture = Texture::new(rgba, device, queue);
         let name = material.name.clone();
         let raw = MaterialRaw::from_tobj(material);
-        let buffer = uniform_buffer(raw.as_std140().as_bytes(), device, Some("Material Buffer"));
-        let (bind_group_layout, bind_group) = MaterialExt::layout(&name.as_str(), &buffer, &device);
-
-        Ok(Self {
-            diffuse_texture,
-            name,
-            buffer,
-            bind_group_layout,
-            bind_group,
-        })
+        let diffuse = obj_texture(material, path).and_then(|texture| load_texture(&texture));
+        Material::create(device, queue, name, raw, diffuse.as_ref().map(|t| &t.0))
     }
 
     pub fn default(device: &wgpu::Device, queue: &wgpu::Queue) -> Result<Self> {
-        let abs = Path::new(".")
-            .join("render/src/textures/blank.png")
-            .canonicalize()?;
-        let rgba = &image::open(abs)?.to_rgba8();
-        let diffuse_texture = Texture::new(rgba, device, queue);
         let name = String::from("Default Material");
         let raw = MaterialRaw::default();
+        Material::create(device, queue, name, raw, None)
+    }
+
+    // material declared in scene file, named after the mesh it's used by
+    pub fn from_desc(
+        device: &wgpu::Device, queue: &wgpu::Queue, mesh: &str, desc: &MaterialDesc,
+    ) -> Result<Self> {
+        let name = format!("{} Material", mesh);
+        let raw = MaterialRaw::from_desc(desc);
+        let diffuse = desc.diffuse_texture.as_deref().and_then(load_texture);
+        Material::create(device, queue, name, raw, diffuse.as_ref().map(|t| &t.0))
+    }
+
+    // base color texture is used as diffuse texture
+    pub fn from_gltf(
+        device: &wgpu::Device, queue: &wgpu::Queue, material: &gltf::Material,
+        diffuse: Option<&RgbaImage>,
+    ) -> Result<Self> {
+        let name = material
+            .name()
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| format!("Material {:?}", material.index()));
+        let raw = MaterialRaw::from_gltf(material);
+        Material::create(device, queue, name, raw, diffuse)
+    }
+
+    // a blank texture is used if there's no diffuse texture
+    fn create(
+        device: &wgpu::Device, queue: &wgpu::Queue, name: String, raw: MaterialRaw,
+        diffuse: Option<&RgbaImage>,
+    ) -> Result<Self> {
+        let diffuse_texture = match diffuse {
+            Some(rgba) => Texture::new(rgba, device, queue),
+            None => {
+                // decoded once, while materials are being created
+                let blank = assets()
+                    .load::<TextureData>(BLANK_TEXTURE)
+                    .context("Failed to load blank texture")?;
+                Texture::new(&blank.0, device, queue)
+            }
+        };
         let buffer = uniform_buffer(raw.as_std140().as_bytes(), device, Some("Material Buffer"));
         let (bind_group_layout, bind_group) = MaterialExt::layout(&name, &buffer, &device);
 
@@ -61,6 +91,27 @@ impl Material {
     }
 }
 
+// diffuse texture of an obj material, relative to the obj file at `path`
+pub(crate) fn obj_texture(material: &tobj::Material, path: &str) -> Option<String> {
+    if material.diffuse_texture.is_empty() {
+        return None;
+    }
+    let dir = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+    let texture = dir.join(&material.diffuse_texture);
+    Some(texture.to_string_lossy().to_string())
+}
+
+// a missing texture is not an error, the blank texture is used instead
+fn load_texture(name: &str) -> Option<Handle<TextureData>> {
+    match assets().load::<TextureData>(name) {
+        Ok(rgba) => Some(rgba),
+        Err(e) => {
+            warn!(logger(), "texture {} missing, use blank: {:?}", name, e);
+            None
+        }
+    }
+}
+
 impl MaterialExt {
     pub fn layout(
         name: &str, buffer: &wgpu::Buffer, device: &wgpu::Device,
@@ -1,4 +1,7 @@
-use crate::Vertex;
+use crate::{NodeId, Vertex};
+use lucien_core::logger::logger;
+use lucien_core::resources::Normals;
+use slog::warn;
 use wgpu::util::DeviceExt;
 
 #[derive(Debug)]
@@ -10,38 +13,59 @@ pub struct Mesh {
 }
 
 impl Mesh {
-    pub fn new(device: &wgpu::Device, mesh: &tobj::Mesh, name: &str) -> Self {
-        let vertices = Vertex::from_tobj(mesh);
+    // normals are computed if the mesh has none
+    pub fn new(device: &wgpu::Device, mesh: &tobj::Mesh, name: &str, normals: Normals) -> Self {
+        let mut vertices = Vertex::from_tobj(mesh);
+        let mut indices = mesh.indices.clone();
+        if mesh.normals.is_empty() {
+            warn!(
+                logger(),
+                "normals missing for mesh {}, use {:?}", name, normals
+            );
+            let (v, i) = Vertex::compute_normals(vertices, indices, normals);
+            vertices = v;
+            indices = i;
+        }
+        Vertex::compute_tangents(&mut vertices, &indices);
+        let material = mesh.material_id.unwrap_or(0);
+        Mesh::from_vertices(device, &vertices, &indices, material, name)
+    }
+
+    pub fn from_vertices(
+        device: &wgpu::Device, vertices: &[Vertex], indices: &[u32], material: usize, name: &str,
+    ) -> Self {
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some(format!("{} vertex buffer", name).as_str()),
-            contents: bytemuck::cast_slice(&vertices),
+            contents: bytemuck::cast_slice(vertices),
             usage: wgpu::BufferUsage::VERTEX,
         });
         let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some(format!("{} index buffer", name).as_str()),
-            contents: bytemuck::cast_slice(&mesh.indices),
+            contents: bytemuck::cast_slice(indices),
             usage: wgpu::BufferUsage::INDEX,
         });
-        let num_indices = mesh.indices.len() as u32;
+        let num_indices = indices.len() as u32;
         Self {
             vertex_buffer,
             index_buffer,
-            material: mesh.material_id.unwrap_or(0),
+            material,
             num_indices,
         }
     }
 }
 
+// A mesh placed in scene, it's drawn with the world matrix of its node
 #[derive(Debug)]
 pub struct Model {
     pub mesh: Mesh,
     pub name: String,
+    pub node: NodeId,
 }
 
 impl Model {
-    pub fn new(device: &wgpu::Device, model: &tobj::Model) -> Self {
+    pub fn new(device: &wgpu::Device, model: &tobj::Model, node: NodeId, normals: Normals) -> Self {
         let name = model.name.as_str().to_string();
-        let mesh = Mesh::new(device, &model.mesh, &name.as_str());
-        Self { mesh, name }
+        let mesh = Mesh::new(device, &model.mesh, &name.as_str(), normals);
+        Self { mesh, name, node }
     }
 }
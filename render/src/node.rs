@@ -0,0 +1,240 @@
+use glam::{Mat4, Quat, Vec3};
+use lucien_core::resources::TransformDesc;
+
+pub type NodeId = usize;
+
+// Local transform relative to parent node
+#[derive(Debug, Clone, Copy)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+// A node in scene graph; models are attached to nodes and drawn
+// with the world matrix of the node.
+#[derive(Debug)]
+pub struct Node {
+    pub name: String,
+    pub transform: Transform,
+    pub parent: Option<NodeId>,
+    pub children: Vec<NodeId>,
+    // world matrix, updated by `SceneGraph::update`
+    pub world: Mat4,
+}
+
+// Node hierarchy. Nodes are never removed, so an id stays valid.
+#[derive(Debug, Default)]
+pub struct SceneGraph {
+    nodes: Vec<Node>,
+    roots: Vec<NodeId>,
+}
+
+impl Transform {
+    pub fn new(translation: Vec3, rotation: Quat, scale: Vec3) -> Self {
+        Self {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    // rotation is euler angles in degrees, in y, x, z order
+    pub fn from_desc(desc: &TransformDesc) -> Self {
+        let [x, y, z] = desc.rotation;
+        Transform::new(
+            desc.translation.into(),
+            Transform::euler(x, y, z),
+            desc.scale.into(),
+        )
+    }
+
+    // rotation from euler angles in degrees, in y, x, z order
+    pub fn euler(x: f32, y: f32, z: f32) -> Quat {
+        Quat::from_rotation_y(y.to_radians())
+            * Quat::from_rotation_x(x.to_radians())
+            * Quat::from_rotation_z(z.to_radians())
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::new(Vec3::ZERO, Quat::IDENTITY, Vec3::ONE)
+    }
+}
+
+impl SceneGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // add a node under parent, or as a root node
+    pub fn add(&mut self, name: &str, transform: Transform, parent: Option<NodeId>) -> NodeId {
+        let id = self.nodes.len();
+        self.nodes.push(Node {
+            name: name.to_string(),
+            transform,
+            parent: None,
+            children: vec![],
+            world: transform.matrix(),
+        });
+        self.roots.push(id);
+        self.set_parent(id, parent);
+        id
+    }
+
+    // move a node under another parent, or make it a root node;
+    // returns false if it would make a cycle
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) -> bool {
+        if let Some(parent) = parent {
+            if self.is_ancestor(id, parent) {
+                return false;
+            }
+        }
+        match self.nodes[id].parent {
+            Some(old) => self.nodes[old].children.retain(|child| *child != id),
+            None => self.roots.retain(|root| *root != id),
+        }
+        match parent {
+            Some(parent) => self.nodes[parent].children.push(id),
+            None => self.roots.push(id),
+        }
+        self.nodes[id].parent = parent;
+        true
+    }
+
+    // if `ancestor` is `id` itself or on the path from `id` to root
+    fn is_ancestor(&self, ancestor: NodeId, id: NodeId) -> bool {
+        let mut current = Some(id);
+        while let Some(node) = current {
+            if node == ancestor {
+                return true;
+            }
+            current = self.nodes[node].parent;
+        }
+        false
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<&Node> {
+        self.nodes.get(id)
+    }
+
+    pub fn get_mut(&mut self, id: NodeId) -> Option<&mut Node> {
+        self.nodes.get_mut(id)
+    }
+
+    // first node with the name
+    pub fn find(&self, name: &str) -> Option<NodeId> {
+        self.nodes.iter().position(|node| node.name == name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    // world matrix of a node, as of last update
+    pub fn world(&self, id: NodeId) -> Mat4 {
+        self.nodes
+            .get(id)
+            .map(|node| node.world)
+            .unwrap_or(Mat4::IDENTITY)
+    }
+
+    // propagate local transforms from roots down to leaves
+    pub fn update(&mut self) {
+        let mut stack: Vec<(NodeId, Mat4)> = self
+            .roots
+            .iter()
+            .map(|root| (*root, Mat4::IDENTITY))
+            .collect();
+        while let Some((id, parent_world)) = stack.pop() {
+            let node = &mut self.nodes[id];
+            node.world = parent_world * node.transform.matrix();
+            let world = node.world;
+            stack.extend(node.children.iter().map(|child| (*child, world)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: f32, y: f32, z: f32) -> Transform {
+        Transform::new(Vec3::new(x, y, z), Quat::IDENTITY, Vec3::ONE)
+    }
+
+    fn position(graph: &SceneGraph, id: NodeId) -> Vec3 {
+        graph.world(id).transform_point3(Vec3::ZERO)
+    }
+
+    #[test]
+    fn transforms_propagate_down_a_chain() {
+        let mut graph = SceneGraph::new();
+        let sun = graph.add("sun", at(1.0, 0.0, 0.0), None);
+        let planet = graph.add(
+            "planet",
+            Transform::new(
+                Vec3::new(0.0, 0.0, 2.0),
+                Transform::euler(0.0, 90.0, 0.0),
+                Vec3::splat(2.0),
+            ),
+            Some(sun),
+        );
+        let moon = graph.add("moon", at(0.0, 0.0, 1.0), Some(planet));
+        graph.update();
+
+        assert!(position(&graph, sun).abs_diff_eq(Vec3::new(1.0, 0.0, 0.0), 1e-5));
+        assert!(position(&graph, planet).abs_diff_eq(Vec3::new(1.0, 0.0, 2.0), 1e-5));
+        // rotated 90 degrees around y and scaled by the planet
+        assert!(position(&graph, moon).abs_diff_eq(Vec3::new(3.0, 0.0, 2.0), 1e-5));
+        assert_eq!(graph.find("moon"), Some(moon));
+        assert_eq!(graph.len(), 3);
+    }
+
+    #[test]
+    fn reparenting_moves_a_subtree() {
+        let mut graph = SceneGraph::new();
+        let a = graph.add("a", at(1.0, 0.0, 0.0), None);
+        let b = graph.add("b", at(0.0, 5.0, 0.0), None);
+        let child = graph.add("child", at(0.0, 0.0, 1.0), Some(a));
+        let leaf = graph.add("leaf", at(0.0, 0.0, 1.0), Some(child));
+
+        assert!(graph.set_parent(child, Some(b)));
+        graph.update();
+        assert!(graph.get(a).unwrap().children.is_empty());
+        assert_eq!(graph.get(b).unwrap().children, vec![child]);
+        assert_eq!(graph.get(child).unwrap().parent, Some(b));
+        assert!(position(&graph, leaf).abs_diff_eq(Vec3::new(0.0, 5.0, 2.0), 1e-5));
+
+        // back to a root node
+        assert!(graph.set_parent(child, None));
+        graph.update();
+        assert!(graph.get(b).unwrap().children.is_empty());
+        assert!(position(&graph, leaf).abs_diff_eq(Vec3::new(0.0, 0.0, 2.0), 1e-5));
+    }
+
+    #[test]
+    fn cycles_are_rejected() {
+        let mut graph = SceneGraph::new();
+        let root = graph.add("root", at(0.0, 1.0, 0.0), None);
+        let middle = graph.add("middle", at(0.0, 1.0, 0.0), Some(root));
+        let leaf = graph.add("leaf", at(0.0, 1.0, 0.0), Some(middle));
+
+        assert!(!graph.set_parent(root, Some(leaf)));
+        assert!(!graph.set_parent(middle, Some(middle)));
+        // the graph is unchanged
+        assert_eq!(graph.get(root).unwrap().parent, None);
+        assert_eq!(graph.get(middle).unwrap().parent, Some(root));
+        graph.update();
+        assert!(position(&graph, leaf).abs_diff_eq(Vec3::new(0.0, 3.0, 0.0), 1e-5));
+    }
+}
@@ -1,6 +1,5 @@
-use crate::Vertex;
+use crate::{shader, Instances, Vertex, VertexLayout};
 use anyhow::{Context, Result};
-use lucien_core::resources::loader;
 
 #[derive(Debug, Clone, Copy)]
 pub enum RenderMode {
@@ -55,31 +54,17 @@ impl Pipeline {
     fn load_shaders(
         device: &wgpu::Device, shader_name: &str,
     ) -> Result<(wgpu::ShaderModule, wgpu::ShaderModule)> {
-        let vs_src = loader()?
-            .load_text(format!("{}.vert.glsl", shader_name).as_str())
-            .context("Failed to load vert shader")?;
-        let fs_src = loader()?
-            .load_text(format!("{}.frag.glsl", shader_name).as_str())
-            .context("Failed to load frag shader")?;
-        let mut compiler = shaderc::Compiler::new().context("Failed to compile shader")?;
-        let vs_spirv = compiler
-            .compile_into_spirv(
-                vs_src.as_str(),
-                shaderc::ShaderKind::Vertex,
-                "shader.vert",
-                "main",
-                None,
-            )
-            .unwrap();
-        let fs_spirv = compiler
-            .compile_into_spirv(
-                fs_src.as_str(),
-                shaderc::ShaderKind::Fragment,
-                "shader.frag",
-                "main",
-                None,
-            )
-            .unwrap();
+        let mut compiler = shaderc::Compiler::new().context("Failed to create shader compiler")?;
+        let vs_spirv = shader::compile(
+            &mut compiler,
+            &format!("{}.vert.glsl", shader_name),
+            shaderc::ShaderKind::Vertex,
+        )?;
+        let fs_spirv = shader::compile(
+            &mut compiler,
+            &format!("{}.frag.glsl", shader_name),
+            shaderc::ShaderKind::Fragment,
+        )?;
         let vs_data = wgpu::util::make_spirv(vs_spirv.as_binary_u8());
         let fs_data = wgpu::util::make_spirv(fs_spirv.as_binary_u8());
         let vs_module = device.create_shader_module(vs_data);
@@ -126,7 +111,7 @@ impl Pipeline {
             }),
             vertex_state: wgpu::VertexStateDescriptor {
                 index_format: wgpu::IndexFormat::Uint32,
-                vertex_buffers: &[Vertex::desc()],
+                vertex_buffers: &[Vertex::desc(), Instances::desc()],
             },
             sample_count: 1,
             sample_mask: !0,
@@ -1,15 +1,21 @@
-use crate::{DepthTexture, Pipeline, RenderMode, RenderTarget, RenderTexture, Scene, Uniforms};
+use crate::{
+    DepthTexture, Pipeline, RenderMode, RenderTarget, RenderTexture, Scene, SharedScene, Uniforms,
+};
 use anyhow::{Context, Result};
+use lucien_core::resources::SceneDesc;
+use std::sync::{Arc, Mutex};
 use time::Instant;
 
 pub type RgbaBuffer = image::ImageBuffer<image::Rgba<u8>, Vec<u8>>;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Clone)]
 pub struct RenderSettings {
     pub size: [u32; 2],
     pub render_mode: RenderMode,
     pub render_target: RenderTarget,
     pub clear_color: Option<wgpu::Color>,
+    // directory to load shaders from, relative to project root
+    pub shader_dir: String,
 }
 // Renderer accepts a RenderSettings, writes data to a RenderState
 // rt is a render texture (render target)
@@ -22,7 +28,7 @@ pub struct RenderState {
     pub start_at: Instant,
     depth: DepthTexture,
     uniforms: Uniforms,
-    scene: Scene,
+    scene: SharedScene,
 }
 
 // Render to render texture, and you can read it to render buffer.
@@ -34,6 +40,9 @@ pub struct Renderer {
     pub textured_pipeline: wgpu::RenderPipeline,
     pub wireframe_pipeline: wgpu::RenderPipeline,
     pub state: RenderState,
+    // kept to rebuild pipelines when shaders change
+    pipeline_layout: wgpu::PipelineLayout,
+    shader: String,
 }
 
 // Send for indicating wwnership may be transferred to another thread,
@@ -43,25 +52,24 @@ pub struct Renderer {
 unsafe impl Send for Renderer {}
 
 impl Renderer {
-    // use first model & material to create pipeline memory layout
+    // use first material to create pipeline memory layout,
+    // all materials share the same bind group layout
     pub fn new(
-        device: &wgpu::Device, queue: &wgpu::Queue, settings: &RenderSettings,
+        device: &wgpu::Device, queue: &wgpu::Queue, settings: &RenderSettings, desc: &SceneDesc,
     ) -> Result<Self> {
         let size = settings.size;
-        // todo remove hard code
-        let scene = Scene::new(device)
-            .context("Failed to create scene")?
-            .load("bunny.obj", device, queue)
-            .context("Failed to load scene")?;
+        let aspect_ratio = size[0] as f32 / size[1] as f32;
+        let scene = Scene::from_desc(desc, aspect_ratio, device, queue)
+            .context("Failed to create scene")?;
         let state =
             RenderState::new(size, &device, scene).context("Failed to create render state")?;
-        let mesh = &state.scene.models[0].mesh;
-        let material = &state.scene.materials[mesh.material];
+        let scene = state.scene.lock().unwrap();
+        let material = &scene.materials[0];
 
         let mut bind_group_layouts = vec![&state.uniforms.bind_group_layout];
         bind_group_layouts.push(&material.diffuse_texture.layout);
         bind_group_layouts.push(&material.bind_group_layout);
-        bind_group_layouts.push(&state.scene.light.bind_group_layout);
+        bind_group_layouts.push(&scene.lights.bind_group_layout);
 
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
@@ -69,38 +77,48 @@ impl Renderer {
                 bind_group_layouts: &bind_group_layouts[..],
                 push_constant_ranges: &[],
             });
-        let default_shader = "shaders/normal";
-        let textured_pipeline =
-            Pipeline::textured(&render_pipeline_layout, &device, default_shader)
-                .context("Failed to create pipeline")?;
-        let wireframe_pipeline =
-            Pipeline::wireframe(&render_pipeline_layout, &device, default_shader)
-                .context("Failed to create pipeline")?;
+        let shader = format!("{}/normal", settings.shader_dir);
+        let textured_pipeline = Pipeline::textured(&render_pipeline_layout, &device, &shader)
+            .context("Failed to create pipeline")?;
+        let wireframe_pipeline = Pipeline::wireframe(&render_pipeline_layout, &device, &shader)
+            .context("Failed to create pipeline")?;
+        drop(scene);
 
         Ok(Self {
             size,
             textured_pipeline,
             wireframe_pipeline,
             state,
+            pipeline_layout: render_pipeline_layout,
+            shader,
         })
     }
 
+    // compile shaders again and rebuild pipelines; pipelines are only
+    // replaced if both compile, so a broken shader keeps the old ones
+    pub fn reload_shaders(&mut self, device: &wgpu::Device) -> Result<()> {
+        let textured_pipeline = Pipeline::textured(&self.pipeline_layout, device, &self.shader)
+            .context("Failed to rebuild pipeline")?;
+        let wireframe_pipeline = Pipeline::wireframe(&self.pipeline_layout, device, &self.shader)
+            .context("Failed to rebuild pipeline")?;
+        self.textured_pipeline = textured_pipeline;
+        self.wireframe_pipeline = wireframe_pipeline;
+        Ok(())
+    }
+
+    // Send the scene to gpu, scripts change the scene before update
     pub fn update(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
         let mut encoder = self.create_encoder(Some("Update Encoder"), device);
+        let mut lock = self.state.scene.lock().unwrap();
+        let scene = &mut *lock;
 
-        // update the game state here
-        // todo replace with actual game logic
-        let time = self.state.start_at.elapsed().as_seconds_f32();
-
-        self.state.scene.camera.eye.x = time.sin() * 5.0;
-        self.state.scene.camera.eye.z = time.cos() * 5.0;
-        self.state.scene.camera.update_view_matrix();
-        self.state.scene.light.position = self.state.scene.camera.eye;
-
+        // systems move camera and lights, so they run first
+        scene.update_transforms(&mut encoder, device);
+        scene.camera.update_view_matrix();
         self.state
             .uniforms
-            .update_buffer(&self.state.scene, &mut encoder, device);
-        self.state.scene.light.update_buffer(&mut encoder, device);
+            .update_buffer(scene, &mut encoder, device);
+        scene.lights.update_buffer(&mut encoder, device);
 
         queue.submit(std::iter::once(encoder.finish()));
     }
@@ -110,13 +128,11 @@ impl Renderer {
     pub fn render(
         &self, settings: &RenderSettings, device: &wgpu::Device, queue: &wgpu::Queue,
     ) -> Result<()> {
+        let scene = self.state.scene.lock().unwrap();
+        let clear = settings.clear_color.unwrap_or(scene.clear_color);
         let mut encoder = self.create_encoder(Some("Render Encoder"), device);
         {
-            let mut render_pass = self.create_render_pass(settings, &mut encoder);
-            // render first material for first mesh
-            let mesh = &self.state.scene.models[0].mesh;
-            let material = &self.state.scene.materials[mesh.material];
-
+            let mut render_pass = self.create_render_pass(clear, &mut encoder);
             match settings.render_mode {
                 RenderMode::Default => {
                     render_pass.set_pipeline(&self.textured_pipeline);
@@ -125,13 +141,7 @@ impl Renderer {
                     render_pass.set_pipeline(&self.wireframe_pipeline);
                 }
             }
-            render_pass.set_bind_group(0, &self.state.uniforms.bind_group, &[]);
-            render_pass.set_bind_group(1, &material.diffuse_texture.group, &[]);
-            render_pass.set_bind_group(2, &material.bind_group, &[]);
-            render_pass.set_bind_group(3, &self.state.scene.light.bind_group, &[]);
-            render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
-            render_pass.set_index_buffer(mesh.index_buffer.slice(..));
-            render_pass.draw_indexed(0..mesh.num_indices, 0, 0..1);
+            self.draw_models(&scene, &mut render_pass);
         }
         queue.submit(std::iter::once(encoder.finish()));
         Ok(())
@@ -142,24 +152,36 @@ impl Renderer {
         &self, target: &wgpu::SwapChainTexture, settings: &RenderSettings, device: &wgpu::Device,
         queue: &wgpu::Queue,
     ) -> Result<()> {
+        let scene = self.state.scene.lock().unwrap();
+        let clear = settings.clear_color.unwrap_or(scene.clear_color);
         let mut encoder = self.create_encoder(Some("Render Encoder"), device);
         {
-            let mut render_pass = self.create_render_pass_external(target, settings, &mut encoder);
-            // render first material for first mesh
-            let mesh = &self.state.scene.models[0].mesh;
-            let material = &self.state.scene.materials[mesh.material];
-
+            let mut render_pass = self.create_render_pass_external(target, clear, &mut encoder);
             render_pass.set_pipeline(&self.textured_pipeline);
-            render_pass.set_bind_group(0, &self.state.uniforms.bind_group, &[]);
+            self.draw_models(&scene, &mut render_pass);
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+        Ok(())
+    }
+
+    // Draw every model in the scene, each mesh binds its own material
+    // and texture; uniforms and lights are shared by the whole pass.
+    fn draw_models<'a>(&'a self, scene: &'a Scene, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_bind_group(0, &self.state.uniforms.bind_group, &[]);
+        render_pass.set_bind_group(3, &scene.lights.bind_group, &[]);
+        // draw list entry i reads its model matrix from instance i
+        render_pass.set_vertex_buffer(1, scene.instances.buffer.slice(..));
+        for (i, model) in scene.draw_list.iter().enumerate() {
+            let mesh = &scene.models[*model].mesh;
+            let material = &scene.materials[mesh.material];
+
             render_pass.set_bind_group(1, &material.diffuse_texture.group, &[]);
             render_pass.set_bind_group(2, &material.bind_group, &[]);
-            render_pass.set_bind_group(3, &self.state.scene.light.bind_group, &[]);
             render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
             render_pass.set_index_buffer(mesh.index_buffer.slice(..));
-            render_pass.draw_indexed(0..mesh.num_indices, 0, 0..1);
+            let instance = i as u32;
+            render_pass.draw_indexed(0..mesh.num_indices, 0, instance..instance + 1);
         }
-        queue.submit(std::iter::once(encoder.finish()));
-        Ok(())
     }
 
     // Save render result from render texture to render buffer,
@@ -202,9 +224,8 @@ impl Renderer {
 
     // Create a render pass, you are subjective to call encoder.finish after this
     fn create_render_pass<'a>(
-        &'a self, settings: &RenderSettings, encoder: &'a mut wgpu::CommandEncoder,
+        &'a self, clear: wgpu::Color, encoder: &'a mut wgpu::CommandEncoder,
     ) -> wgpu::RenderPass<'a> {
-        let clear = settings.get_clear_color();
         let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             // write colors to render target
             color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
@@ -230,10 +251,9 @@ impl Renderer {
 
     // Create render pass for external render target
     fn create_render_pass_external<'a>(
-        &'a self, target: &'a wgpu::SwapChainTexture, settings: &RenderSettings,
+        &'a self, target: &'a wgpu::SwapChainTexture, clear: wgpu::Color,
         encoder: &'a mut wgpu::CommandEncoder,
     ) -> wgpu::RenderPass<'a> {
-        let clear = settings.get_clear_color();
         let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             // write colors to render target
             color_attachments: &[wgpu::RenderPassColorAttachmentDescriptor {
@@ -257,6 +277,11 @@ impl Renderer {
         render_pass
     }
 
+    // Scene is shared so scripts can change it between frames
+    pub fn scene(&self) -> SharedScene {
+        Arc::clone(&self.state.scene)
+    }
+
     // Convert current render result from render buffer to rgba
     pub async fn as_rgba(&self, device: &wgpu::Device) -> Result<RgbaBuffer> {
         self.state.as_rgba(device).await
@@ -270,17 +295,9 @@ impl RenderSettings {
             render_target: RenderTarget::RenderTexture,
             render_mode: RenderMode::Default,
             clear_color: None,
+            shader_dir: "shaders".to_string(),
         }
     }
-
-    pub fn get_clear_color(&self) -> wgpu::Color {
-        self.clear_color.unwrap_or(wgpu::Color {
-            r: 0.1,
-            g: 0.1,
-            b: 0.1,
-            a: 1.0,
-        })
-    }
 }
 
 impl Default for RenderSettings {
@@ -306,7 +323,7 @@ impl RenderState {
             size,
             uniforms,
             depth,
-            scene,
+            scene: Arc::new(Mutex::new(scene)),
             start_at,
         })
     }
@@ -1,60 +1,284 @@
-use crate::{AmbientLight, Camera, Material, Model, PointLight};
-use anyhow::Result;
+use crate::material::obj_texture;
+use crate::{
+    camera_system, light_system, AmbientLight, Camera, Instances, Light, Lights, Material,
+    MeshRenderer, Model, Name, NodeId, SceneGraph, SceneNode, Transform,
+};
+use anyhow::{anyhow, Context, Result};
+use glam::Mat4;
+use lucien_core::ecs::{Entity, Schedule, World};
+use std::sync::{Arc, Mutex};
 
 use lucien_core::logger::logger;
-use lucien_core::resources::loader;
+use lucien_core::resources::{assets, MeshData, MeshDesc, SceneDesc, TextureData};
 use slog::warn;
 
+// Scene is shared between renderer and scripts
+pub type SharedScene = Arc<Mutex<Scene>>;
+
 #[derive(Debug)]
 pub struct Scene {
     pub camera: Camera,
-    pub light: PointLight, // todo: multiple lights
+    pub lights: Lights,
     pub ambient_light: AmbientLight,
+    pub clear_color: wgpu::Color,
+    pub nodes: SceneGraph,
     pub models: Vec<Model>,
     pub materials: Vec<Material>,
+    pub instances: Instances,
+    // game objects, every loaded model is drawn by an entity
+    pub world: World,
+    // systems run on every update, before drawing
+    pub systems: Schedule<Scene>,
+    // model index of each instance drawn, built on update
+    pub draw_list: Vec<usize>,
 }
 
 impl Scene {
     pub fn new(device: &wgpu::Device) -> Result<Self> {
+        let nodes = SceneGraph::new();
         let models = vec![];
         let materials = vec![];
+        let instances = Instances::new(device);
         let camera = Camera::default();
-        let light = PointLight::default(device);
+        let lights = Lights::new(device);
         let ambient_light = AmbientLight::default();
+        let clear_color = wgpu::Color {
+            r: 0.1,
+            g: 0.1,
+            b: 0.1,
+            a: 1.0,
+        };
+        let world = World::new();
+        let mut systems = Schedule::new();
+        systems.add("camera", camera_system());
+        systems.add("lights", light_system());
 
         Ok(Self {
             camera,
-            light,
+            lights,
             ambient_light,
+            clear_color,
+            nodes,
             models,
             materials,
+            instances,
+            world,
+            systems,
+            draw_list: vec![],
         })
     }
 
-    pub fn load(mut self, path: &str, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<Self> {
-        let (obj_models, obj_materials) = loader()?.load_obj(path)?;
-        obj_models.iter().for_each(|model| {
-            self.models.push(Model::new(device, model));
+    // create scene from a scene file
+    pub fn from_desc(
+        desc: &SceneDesc, aspect_ratio: f32, device: &wgpu::Device, queue: &wgpu::Queue,
+    ) -> Result<Self> {
+        let mut scene = Scene::new(device)?;
+        scene.camera = Camera::from_desc(&desc.camera, aspect_ratio);
+        scene.ambient_light = AmbientLight::from_desc(&desc.ambient_light);
+        let [r, g, b, a] = desc.clear_color;
+        scene.clear_color = wgpu::Color { r, g, b, a };
+        for light in desc.lights.iter() {
+            if scene.lights.add(Light::from_desc(light)).is_none() {
+                warn!(logger(), "too many lights in scene, ignored: {:?}", light);
+            }
+        }
+        // files are decoded by loader threads, and uploaded here in order
+        prefetch(&desc.meshes);
+        for mesh in desc.meshes.iter() {
+            scene.load(mesh, None, device, queue)?;
+        }
+        // models are drawn before the first update
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Scene Encoder"),
         });
-        obj_materials.iter().for_each(|material| {
+        scene.update_transforms(&mut encoder, device);
+        queue.submit(std::iter::once(encoder.finish()));
+        // pipeline layout is created from the first material
+        if scene.materials.is_empty() {
+            scene.materials.push(Material::default(device, queue)?);
+        }
+
+        Ok(scene)
+    }
+
+    // add a node under parent, and its children recursively;
+    // returns the node that models in the mesh file are attached to
+    pub fn load(
+        &mut self, desc: &MeshDesc, parent: Option<NodeId>, device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<NodeId> {
+        let name = if desc.name.is_empty() {
+            desc.path.as_str()
+        } else {
+            desc.name.as_str()
+        };
+        let node = self
+            .nodes
+            .add(name, Transform::from_desc(&desc.transform), parent);
+        let path = desc.path.to_lowercase();
+        let first = self.models.len();
+        if path.ends_with(".gltf") || path.ends_with(".glb") {
+            self.load_gltf(desc, node, device, queue)
+                .with_context(|| format!("Failed to load gltf {}", desc.path))?;
+        } else if !path.is_empty() {
+            self.load_models(desc, node, device, queue)
+                .with_context(|| format!("Failed to load mesh {}", desc.path))?;
+        }
+        self.spawn_models(first);
+        for child in desc.children.iter() {
+            self.load(child, Some(node), device, queue)?;
+        }
+
+        Ok(node)
+    }
+
+    // load models in an obj file and attach them to the node
+    fn load_models(
+        &mut self, desc: &MeshDesc, node: NodeId, device: &wgpu::Device, queue: &wgpu::Queue,
+    ) -> Result<()> {
+        let path = desc.path.as_str();
+        let mesh = assets().load::<MeshData>(path)?;
+        let (obj_models, obj_materials) = match &*mesh {
+            MeshData::Obj(models, materials) => (models, materials),
+            MeshData::Gltf(_) => return Err(anyhow!("{} is not an obj file", path)),
+        };
+        // material ids in the file are relative to the file,
+        // offset them by the materials we already have
+        let base = self.materials.len();
+        if let Some(material) = desc.material.as_ref() {
+            // every model uses the material in scene file
             self.materials
-                .push(Material::new(device, queue, material).unwrap());
-        });
-        // if material is missing from the file, use default
-        let n_models = obj_models.len();
+                .push(Material::from_desc(device, queue, path, material)?);
+            obj_models.iter().for_each(|model| {
+                let mut model = Model::new(device, model, node, desc.normals);
+                model.mesh.material = base;
+                self.models.push(model);
+            });
+            return Ok(());
+        }
+
         let n_materials = obj_materials.len();
-        if n_models > n_materials {
+        for material in obj_materials.iter() {
+            if let Some(texture) = obj_texture(material, path) {
+                assets().load_async::<TextureData>(&texture);
+            }
+        }
+        for material in obj_materials.iter() {
+            self.materials
+                .push(Material::new(device, queue, material, path)?);
+        }
+        let mut missing = false;
+        obj_models.iter().for_each(|model| {
+            let mut model = Model::new(device, model, node, desc.normals);
+            if model.mesh.material >= n_materials {
+                missing = true;
+                model.mesh.material = base + n_materials;
+            } else {
+                model.mesh.material += base;
+            }
+            self.models.push(model);
+        });
+        // models may share materials, but if a model refers to a material
+        // missing from the file, point it to a default one
+        if missing {
             let msg = format!("material missing from file {}, use default", path);
             warn!(logger(), "{}", msg);
-            for _ in 0..(n_models - n_materials) {
-                self.materials.push(Material::default(device, queue)?);
+            self.materials.push(Material::default(device, queue)?);
+        }
+
+        Ok(())
+    }
+
+    // spawn an entity drawing each model loaded from `first` on,
+    // attached to the model's node and named by it as in scene file
+    fn spawn_models(&mut self, first: usize) {
+        for i in first..self.models.len() {
+            let node = self.models[i].node;
+            let name = self
+                .nodes
+                .get(node)
+                .map(|node| node.name.clone())
+                .unwrap_or_default();
+            let entity = self.world.spawn();
+            self.world.insert(entity, SceneNode(node));
+            self.world.insert(entity, Transform::default());
+            self.world.insert(entity, MeshRenderer { model: i });
+            self.world.insert(entity, Name(name));
+        }
+    }
+
+    // world matrix of an entity, its transform relative to its node;
+    // None if the entity is despawned
+    pub fn world_matrix(&self, entity: Entity) -> Option<Mat4> {
+        if !self.world.is_alive(entity) {
+            return None;
+        }
+        let parent = match self.world.get::<SceneNode>(entity) {
+            Some(SceneNode(node)) => self.nodes.world(*node),
+            None => Mat4::IDENTITY,
+        };
+        let local = self
+            .world
+            .get::<Transform>(entity)
+            .map(|transform| transform.matrix())
+            .unwrap_or(Mat4::IDENTITY);
+        Some(parent * local)
+    }
+
+    // first entity with the name
+    pub fn find_entity(&self, name: &str) -> Option<Entity> {
+        self.world
+            .query::<Name>()
+            .find(|(_, n)| n.0 == name)
+            .map(|(entity, _)| entity)
+    }
+
+    // model by its node name in scene file, or by its name in mesh file
+    pub fn find_model(&self, name: &str) -> Option<usize> {
+        let node = self.nodes.find(name);
+        self.models
+            .iter()
+            .position(|model| Some(model.node) == node)
+            .or_else(|| self.models.iter().position(|model| model.name == name))
+    }
+
+    // propagate node transforms, run systems, and send matrices
+    // of entities with a mesh to gpu
+    pub fn update_transforms(&mut self, encoder: &mut wgpu::CommandEncoder, device: &wgpu::Device) {
+        self.nodes.update();
+        // systems take the scene, so take them out while they run
+        let mut systems = std::mem::take(&mut self.systems);
+        systems.run(self);
+        self.systems = systems;
+
+        self.draw_list.clear();
+        let mut matrices = vec![];
+        for (entity, mesh) in self.world.query::<MeshRenderer>() {
+            if mesh.model >= self.models.len() {
+                continue;
+            }
+            if let Some(matrix) = self.world_matrix(entity) {
+                self.draw_list.push(mesh.model);
+                matrices.push(matrix);
             }
         }
-        assert!(
-            self.materials.len() == self.models.len(),
-            "Models and materials count not equal!"
-        );
+        self.instances.update_buffer(&matrices, encoder, device);
+    }
+}
 
-        Ok(self)
+// start loading mesh files and textures of the nodes in background
+fn prefetch(meshes: &[MeshDesc]) {
+    for mesh in meshes.iter() {
+        if !mesh.path.is_empty() {
+            assets().load_async::<MeshData>(&mesh.path);
+        }
+        let texture = mesh
+            .material
+            .as_ref()
+            .and_then(|material| material.diffuse_texture.as_ref());
+        if let Some(texture) = texture {
+            assets().load_async::<TextureData>(texture);
+        }
+        prefetch(&mesh.children);
     }
 }
@@ -0,0 +1,243 @@
+use anyhow::{Context, Result};
+use lucien_core::resources::{assets, ShaderSource};
+use std::fmt;
+use std::path::Path;
+
+// A message from shader compiler, e.g. `normal.frag.glsl:12: error: ...`;
+// glslang doesn't always say which line or column
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShaderDiagnostic {
+    pub path: String,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    pub message: String,
+}
+
+// Shader failed to compile, with every diagnostic of the compiler
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShaderError {
+    pub path: String,
+    pub diagnostics: Vec<ShaderDiagnostic>,
+}
+
+impl fmt::Display for ShaderDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.path)?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+        }
+        if let Some(column) = self.column {
+            write!(f, ":{}", column)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "shader {} has errors", self.path)?;
+        for diagnostic in self.diagnostics.iter() {
+            write!(f, "\n  {}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+impl ShaderError {
+    pub fn from_shaderc(path: &str, error: shaderc::Error) -> Self {
+        let diagnostics = match error {
+            shaderc::Error::CompilationError(_, log) => log
+                .lines()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty() && !line.ends_with("generated."))
+                .map(|line| ShaderDiagnostic::parse(path, line))
+                .collect(),
+            error => vec![ShaderDiagnostic::parse(path, &error.to_string())],
+        };
+        Self {
+            path: path.to_string(),
+            diagnostics,
+        }
+    }
+}
+
+impl ShaderDiagnostic {
+    // parse `path:line[:column]: message`, a line in other forms
+    // is the message of the shader at `path`
+    pub fn parse(path: &str, line: &str) -> Self {
+        let mut start = 0;
+        while let Some(i) = line[start..].find(':') {
+            let i = start + i;
+            let (line_no, rest) = split_number(&line[i + 1..]);
+            if let Some(line_no) = line_no {
+                let (column, rest) = split_number(rest);
+                return Self {
+                    path: line[..i].to_string(),
+                    line: Some(line_no),
+                    column,
+                    message: rest.trim().to_string(),
+                };
+            }
+            start = i + 1;
+        }
+        Self {
+            path: path.to_string(),
+            line: None,
+            column: None,
+            message: line.trim().to_string(),
+        }
+    }
+}
+
+// split `12:rest` into 12 and `rest`
+fn split_number(s: &str) -> (Option<u32>, &str) {
+    let digits = s.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 && s[digits..].starts_with(':') {
+        (s[..digits].parse().ok(), &s[digits + 1..])
+    } else {
+        (None, s)
+    }
+}
+
+// Compile a glsl file of the project to spirv. `#include "file"` is
+// relative to the including file, `#include <file>` is relative to
+// project root; both are loaded with the asset server.
+pub fn compile(
+    compiler: &mut shaderc::Compiler, path: &str, kind: shaderc::ShaderKind,
+) -> Result<shaderc::CompilationArtifact> {
+    let src = assets()
+        .load::<ShaderSource>(path)
+        .with_context(|| format!("Failed to load shader {}", path))?;
+    let mut options = shaderc::CompileOptions::new().context("Failed to create shader options")?;
+    options.set_include_callback(|name, include_type, from, _depth| {
+        let resolved = match include_type {
+            shaderc::IncludeType::Relative => Path::new(from)
+                .parent()
+                .unwrap_or_else(|| Path::new(""))
+                .join(name),
+            shaderc::IncludeType::Standard => Path::new(name).to_path_buf(),
+        };
+        let resolved_name = resolved.to_string_lossy().to_string();
+        let content = assets()
+            .load::<ShaderSource>(&resolved_name)
+            .map(|source| source.0.clone())
+            .map_err(|e| format!("{:#}", e))?;
+        Ok(shaderc::ResolvedInclude {
+            resolved_name,
+            content,
+        })
+    });
+
+    compiler
+        .compile_into_spirv(&src.0, kind, path, "main", Some(&options))
+        .map_err(|e| ShaderError::from_shaderc(path, e))
+        .with_context(|| format!("Failed to compile {}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnostic(
+        path: &str, line: Option<u32>, column: Option<u32>, message: &str,
+    ) -> ShaderDiagnostic {
+        ShaderDiagnostic {
+            path: path.to_string(),
+            line,
+            column,
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn parse_shaderc_line() {
+        let parsed = ShaderDiagnostic::parse(
+            "shaders/normal.frag.glsl",
+            "shaders/lights.glsl:12: error: 'light' : undeclared identifier",
+        );
+        assert_eq!(
+            parsed,
+            diagnostic(
+                "shaders/lights.glsl",
+                Some(12),
+                None,
+                "error: 'light' : undeclared identifier"
+            )
+        );
+        assert_eq!(
+            parsed.to_string(),
+            "shaders/lights.glsl:12: error: 'light' : undeclared identifier"
+        );
+    }
+
+    #[test]
+    fn parse_line_and_column() {
+        let parsed = ShaderDiagnostic::parse("a.glsl", "a.glsl:3:14: warning: unused");
+        assert_eq!(
+            parsed,
+            diagnostic("a.glsl", Some(3), Some(14), "warning: unused")
+        );
+        assert_eq!(parsed.to_string(), "a.glsl:3:14: warning: unused");
+    }
+
+    #[test]
+    fn lines_without_a_location() {
+        let parsed = ShaderDiagnostic::parse("a.glsl", "error: #version: bad profile");
+        assert_eq!(
+            parsed,
+            diagnostic("a.glsl", None, None, "error: #version: bad profile")
+        );
+        assert_eq!(parsed.to_string(), "a.glsl: error: #version: bad profile");
+
+        // a number not followed by a colon isn't a line
+        let parsed = ShaderDiagnostic::parse("a.glsl", "a.glsl:12 error");
+        assert_eq!(parsed, diagnostic("a.glsl", None, None, "a.glsl:12 error"));
+    }
+
+    #[test]
+    fn every_error_of_compilation() {
+        let log = "a.glsl:3: error: 'x' : undeclared identifier\n\
+                   a.glsl:7: error: '' : syntax error\n\
+                   2 errors generated.\n";
+        let error =
+            ShaderError::from_shaderc("a.glsl", shaderc::Error::CompilationError(2, log.into()));
+        assert_eq!(error.path, "a.glsl");
+        assert_eq!(
+            error.diagnostics,
+            vec![
+                diagnostic(
+                    "a.glsl",
+                    Some(3),
+                    None,
+                    "error: 'x' : undeclared identifier"
+                ),
+                diagnostic("a.glsl", Some(7), None, "error: '' : syntax error"),
+            ]
+        );
+        assert_eq!(
+            error.to_string(),
+            "shader a.glsl has errors\n  \
+             a.glsl:3: error: 'x' : undeclared identifier\n  \
+             a.glsl:7: error: '' : syntax error"
+        );
+    }
+
+    #[test]
+    fn other_errors_are_one_diagnostic() {
+        let error = ShaderError::from_shaderc(
+            "a.glsl",
+            shaderc::Error::InvalidStage("no stage given".into()),
+        );
+        assert_eq!(
+            error.diagnostics,
+            vec![diagnostic(
+                "a.glsl",
+                None,
+                None,
+                "invalid stage: no stage given"
+            )]
+        );
+    }
+}
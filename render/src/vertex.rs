@@ -1,27 +1,127 @@
 use bytemuck::{Pod, Zeroable};
-use glam::Vec3;
+use glam::{Vec2, Vec3};
 use lucien_core::logger::logger;
+use lucien_core::resources::Normals;
 use slog::warn;
 
+// Declare which field of a vertex struct is which attribute, offsets
+// are computed from the struct, e.g.
+// vertex_layout!(MyVertex { position: Position, color: Color });
+macro_rules! vertex_layout {
+    ($vertex:ty { $($field:ident: $attribute:ident),* $(,)? }) => {
+        impl $crate::VertexLayout for $vertex {
+            const ATTRIBUTES: &'static [wgpu::VertexAttributeDescriptor] = &[
+                $(wgpu::VertexAttributeDescriptor {
+                    offset: std::mem::offset_of!($vertex, $field) as wgpu::BufferAddress,
+                    shader_location: $crate::VertexAttribute::$attribute.location(),
+                    format: $crate::VertexAttribute::$attribute.format(),
+                }),*
+            ];
+        }
+    };
+}
+
+// tangent.w is the handedness of bitangent, 1 or -1
 #[repr(C)]
 #[derive(Default, Debug, Copy, Clone)]
 pub struct Vertex {
     pub position: [f32; 3],
     pub normal: [f32; 3],
     pub tex_coord: [f32; 2],
+    pub tangent: [f32; 4],
 }
 
 unsafe impl Pod for Vertex {}
 unsafe impl Zeroable for Vertex {}
 
-#[allow(dead_code)]
-fn compute_normal(v0: &[f32; 3], v1: &[f32; 3], v2: &[f32; 3]) -> [f32; 3] {
+vertex_layout!(Vertex {
+    position: Position,
+    normal: Normal,
+    tex_coord: TexCoord,
+    tangent: Tangent,
+});
+
+// Attributes a vertex may have. Each has a fixed shader location,
+// so shaders don't depend on which other attributes a vertex has.
+// Locations after the last one are for instance data.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VertexAttribute {
+    Position,
+    Normal,
+    TexCoord,
+    Tangent,
+    Color,
+    TexCoord1,
+    Joints,
+    Weights,
+}
+
+impl VertexAttribute {
+    pub const fn location(self) -> wgpu::ShaderLocation {
+        match self {
+            VertexAttribute::Position => 0,
+            VertexAttribute::Normal => 1,
+            VertexAttribute::TexCoord => 2,
+            VertexAttribute::Tangent => 3,
+            VertexAttribute::Color => 4,
+            VertexAttribute::TexCoord1 => 5,
+            VertexAttribute::Joints => 6,
+            VertexAttribute::Weights => 7,
+        }
+    }
+
+    // field type in vertex struct must have the same size:
+    // [f32; 3], [f32; 2], [f32; 4], or [u16; 4] for joints
+    pub const fn format(self) -> wgpu::VertexFormat {
+        match self {
+            VertexAttribute::Position | VertexAttribute::Normal => wgpu::VertexFormat::Float3,
+            VertexAttribute::TexCoord | VertexAttribute::TexCoord1 => wgpu::VertexFormat::Float2,
+            VertexAttribute::Tangent | VertexAttribute::Color | VertexAttribute::Weights => {
+                wgpu::VertexFormat::Float4
+            }
+            VertexAttribute::Joints => wgpu::VertexFormat::Ushort4,
+        }
+    }
+}
+
+// Vertex buffer layout of a vertex struct, implement it with `vertex_layout!`
+pub trait VertexLayout: Pod {
+    const ATTRIBUTES: &'static [wgpu::VertexAttributeDescriptor];
+
+    fn desc<'a>() -> wgpu::VertexBufferDescriptor<'a> {
+        wgpu::VertexBufferDescriptor {
+            stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Vertex,
+            attributes: Self::ATTRIBUTES,
+        }
+    }
+}
+
+// length of the normal is twice the area of the triangle,
+// so adding them up weights normals by area
+fn face_normal(v0: &[f32; 3], v1: &[f32; 3], v2: &[f32; 3]) -> Vec3 {
     let n0 = Vec3::from(*v0);
     let n1 = Vec3::from(*v1);
     let n2 = Vec3::from(*v2);
     let f0 = n0 - n2;
     let f2 = n1 - n2;
-    f0.cross(f2).normalize().into()
+    f0.cross(f2)
+}
+
+// degenerate triangles have no normal, they get zero
+fn compute_normal(v0: &[f32; 3], v1: &[f32; 3], v2: &[f32; 3]) -> [f32; 3] {
+    let normal = face_normal(v0, v1, v2);
+    if normal.length_squared() > 0.0 {
+        normal.normalize().into()
+    } else {
+        [0.0, 0.0, 0.0]
+    }
+}
+
+// any unit vector perpendicular to n
+fn perpendicular(n: Vec3) -> Vec3 {
+    let axis = if n.x.abs() < 0.9 { Vec3::X } else { Vec3::Y };
+    n.cross(axis).normalize()
 }
 
 impl Vertex {
@@ -48,41 +148,296 @@ impl Vertex {
                 } else {
                     [0.0, 0.0]
                 },
+                tangent: [0.0, 0.0, 0.0, 0.0],
             });
         }
         let logger = logger();
 
-        // todo calculate normals after I figured out what is the correct face...
         if mesh.texcoords.is_empty() {
             warn!(logger, "texture coord missing for mesh");
         }
-        if mesh.normals.is_empty() {
-            warn!(logger, "normals missing for mesh");
-        }
         vertices
     }
 
-    pub fn desc<'a>() -> wgpu::VertexBufferDescriptor<'a> {
-        wgpu::VertexBufferDescriptor {
-            stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-            step_mode: wgpu::InputStepMode::Vertex,
-            attributes: &[
-                wgpu::VertexAttributeDescriptor {
-                    offset: 0,
-                    shader_location: 0,
-                    format: wgpu::VertexFormat::Float3,
-                },
-                wgpu::VertexAttributeDescriptor {
-                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
-                    shader_location: 1,
-                    format: wgpu::VertexFormat::Float3,
-                },
-                wgpu::VertexAttributeDescriptor {
-                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
-                    shader_location: 2,
-                    format: wgpu::VertexFormat::Float2,
-                },
-            ],
+    // Compute normals from triangles, faces are counter clockwise.
+    // Flat normals need a vertex per face corner, so vertices are unshared
+    // and indices are rebuilt.
+    pub fn compute_normals(
+        vertices: Vec<Vertex>, indices: Vec<u32>, normals: Normals,
+    ) -> (Vec<Vertex>, Vec<u32>) {
+        match normals {
+            Normals::Flat => {
+                let mut flat: Vec<Vertex> = Vec::with_capacity(indices.len());
+                for face in indices.chunks_exact(3) {
+                    let [v0, v1, v2] = [
+                        vertices[face[0] as usize],
+                        vertices[face[1] as usize],
+                        vertices[face[2] as usize],
+                    ];
+                    let normal = compute_normal(&v0.position, &v1.position, &v2.position);
+                    for mut vertex in [v0, v1, v2].iter().copied() {
+                        vertex.normal = normal;
+                        flat.push(vertex);
+                    }
+                }
+                let indices = (0..flat.len() as u32).collect();
+                (flat, indices)
+            }
+            Normals::Smooth => {
+                let mut sums = vec![Vec3::ZERO; vertices.len()];
+                for face in indices.chunks_exact(3) {
+                    let normal = face_normal(
+                        &vertices[face[0] as usize].position,
+                        &vertices[face[1] as usize].position,
+                        &vertices[face[2] as usize].position,
+                    );
+                    face.iter().for_each(|i| sums[*i as usize] += normal);
+                }
+                let mut vertices = vertices;
+                for (vertex, sum) in vertices.iter_mut().zip(sums) {
+                    if sum.length_squared() > 0.0 {
+                        vertex.normal = sum.normalize().into();
+                    }
+                }
+                (vertices, indices)
+            }
+        }
+    }
+
+    // Compute tangents from texture coords for normal mapping, they are
+    // orthogonalized against normals. Vertices without texture coords
+    // get an arbitrary tangent.
+    pub fn compute_tangents(vertices: &mut [Vertex], indices: &[u32]) {
+        let mut tangents = vec![Vec3::ZERO; vertices.len()];
+        let mut bitangents = vec![Vec3::ZERO; vertices.len()];
+        for face in indices.chunks_exact(3) {
+            let [v0, v1, v2] = [
+                vertices[face[0] as usize],
+                vertices[face[1] as usize],
+                vertices[face[2] as usize],
+            ];
+            let e1 = Vec3::from(v1.position) - Vec3::from(v0.position);
+            let e2 = Vec3::from(v2.position) - Vec3::from(v0.position);
+            let d1 = Vec2::from(v1.tex_coord) - Vec2::from(v0.tex_coord);
+            let d2 = Vec2::from(v2.tex_coord) - Vec2::from(v0.tex_coord);
+            let det = d1.x * d2.y - d2.x * d1.y;
+            if det.abs() < f32::EPSILON {
+                continue;
+            }
+            let r = 1.0 / det;
+            let tangent = (e1 * d2.y - e2 * d1.y) * r;
+            let bitangent = (e2 * d1.x - e1 * d2.x) * r;
+            for i in face.iter() {
+                tangents[*i as usize] += tangent;
+                bitangents[*i as usize] += bitangent;
+            }
+        }
+        for (i, vertex) in vertices.iter_mut().enumerate() {
+            let n = Vec3::from(vertex.normal);
+            let t = tangents[i] - n * n.dot(tangents[i]);
+            let t = if t.length_squared() > 0.0 {
+                t.normalize()
+            } else {
+                perpendicular(n)
+            };
+            let w = if n.cross(t).dot(bitangents[i]) < 0.0 {
+                -1.0
+            } else {
+                1.0
+            };
+            vertex.tangent = [t.x, t.y, t.z, w];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::size_of;
+
+    // a vertex with every optional attribute
+    #[repr(C)]
+    #[derive(Default, Debug, Copy, Clone)]
+    struct FullVertex {
+        position: [f32; 3],
+        normal: [f32; 3],
+        tex_coord: [f32; 2],
+        tangent: [f32; 4],
+        color: [f32; 4],
+        tex_coord1: [f32; 2],
+        joints: [u16; 4],
+        weights: [f32; 4],
+    }
+
+    unsafe impl Pod for FullVertex {}
+    unsafe impl Zeroable for FullVertex {}
+
+    vertex_layout!(FullVertex {
+        position: Position,
+        normal: Normal,
+        tex_coord: TexCoord,
+        tangent: Tangent,
+        color: Color,
+        tex_coord1: TexCoord1,
+        joints: Joints,
+        weights: Weights,
+    });
+
+    // attributes fit in the stride, don't overlap, and have unique locations
+    fn check_layout<V: VertexLayout>() {
+        let desc = V::desc();
+        let stride = size_of::<V>() as wgpu::BufferAddress;
+        assert_eq!(desc.stride, stride);
+
+        let mut ranges: Vec<(wgpu::BufferAddress, wgpu::BufferAddress)> = desc
+            .attributes
+            .iter()
+            .map(|attr| (attr.offset, attr.offset + attr.format.size()))
+            .collect();
+        ranges.sort();
+        assert!(ranges.iter().all(|(_, end)| *end <= stride));
+        assert!(ranges.windows(2).all(|pair| pair[0].1 <= pair[1].0));
+
+        let mut locations: Vec<u32> = desc
+            .attributes
+            .iter()
+            .map(|attr| attr.shader_location)
+            .collect();
+        locations.sort_unstable();
+        locations.dedup();
+        assert_eq!(locations.len(), desc.attributes.len());
+    }
+
+    #[test]
+    fn vertex_offsets() {
+        let offsets: Vec<wgpu::BufferAddress> =
+            Vertex::ATTRIBUTES.iter().map(|attr| attr.offset).collect();
+        assert_eq!(offsets, vec![0, 12, 24, 32]);
+        assert_eq!(size_of::<Vertex>(), 48);
+    }
+
+    #[test]
+    fn tex_coord_after_normal() {
+        let normal = &Vertex::ATTRIBUTES[1];
+        let tex_coord = &Vertex::ATTRIBUTES[2];
+        assert_eq!(normal.offset + normal.format.size(), tex_coord.offset);
+    }
+
+    #[test]
+    fn vertex_layout() {
+        check_layout::<Vertex>();
+    }
+
+    #[test]
+    fn full_vertex_layout() {
+        check_layout::<FullVertex>();
+        assert_eq!(FullVertex::ATTRIBUTES.len(), 8);
+    }
+
+    #[test]
+    fn locations_before_instance_data() {
+        // instance model matrix starts at location 8
+        assert!(FullVertex::ATTRIBUTES
+            .iter()
+            .all(|attr| attr.shader_location < 8));
+    }
+
+    fn vertex(x: f32, y: f32, z: f32, u: f32, v: f32) -> Vertex {
+        Vertex {
+            position: [x, y, z],
+            tex_coord: [u, v],
+            ..Default::default()
+        }
+    }
+
+    // unit quad on the xy plane facing +z, two triangles sharing a diagonal
+    fn quad() -> (Vec<Vertex>, Vec<u32>) {
+        let vertices = vec![
+            vertex(0.0, 0.0, 0.0, 0.0, 1.0),
+            vertex(1.0, 0.0, 0.0, 1.0, 1.0),
+            vertex(1.0, 1.0, 0.0, 1.0, 0.0),
+            vertex(0.0, 1.0, 0.0, 0.0, 0.0),
+        ];
+        (vertices, vec![0, 1, 2, 0, 2, 3])
+    }
+
+    #[test]
+    fn flat_normals_unshare_vertices() {
+        let (vertices, indices) = quad();
+        let (vertices, indices) = Vertex::compute_normals(vertices, indices, Normals::Flat);
+        assert_eq!(vertices.len(), 6);
+        assert_eq!(indices, vec![0, 1, 2, 3, 4, 5]);
+        assert!(vertices.iter().all(|v| v.normal == [0.0, 0.0, 1.0]));
+        assert_eq!(vertices[4].position, [1.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn smooth_normals_are_shared() {
+        // two faces of a cube meeting at the edge x = 1, z = 0
+        let vertices = vec![
+            vertex(0.0, 0.0, 0.0, 0.0, 0.0),
+            vertex(1.0, 0.0, 0.0, 0.0, 0.0),
+            vertex(1.0, 1.0, 0.0, 0.0, 0.0),
+            vertex(1.0, 0.0, -1.0, 0.0, 0.0),
+        ];
+        let indices = vec![0, 1, 2, 1, 3, 2];
+        let (vertices, indices) = Vertex::compute_normals(vertices, indices, Normals::Smooth);
+        assert_eq!(vertices.len(), 4);
+        assert_eq!(indices, vec![0, 1, 2, 1, 3, 2]);
+        assert_eq!(vertices[0].normal, [0.0, 0.0, 1.0]);
+        assert_eq!(vertices[3].normal, [1.0, 0.0, 0.0]);
+        // shared vertices average the faces
+        let edge = Vec3::new(1.0, 0.0, 1.0).normalize();
+        assert!(Vec3::from(vertices[1].normal).abs_diff_eq(edge, 1e-6));
+        assert!(Vec3::from(vertices[2].normal).abs_diff_eq(edge, 1e-6));
+    }
+
+    #[test]
+    fn degenerate_triangles_have_no_normal() {
+        let vertices = vec![
+            vertex(0.0, 0.0, 0.0, 0.0, 0.0),
+            vertex(1.0, 1.0, 1.0, 0.0, 0.0),
+            vertex(2.0, 2.0, 2.0, 0.0, 0.0),
+        ];
+        let (flat, _) = Vertex::compute_normals(vertices.clone(), vec![0, 1, 2], Normals::Flat);
+        assert!(flat.iter().all(|v| v.normal == [0.0, 0.0, 0.0]));
+        let (smooth, _) = Vertex::compute_normals(vertices, vec![0, 1, 2], Normals::Smooth);
+        assert!(smooth.iter().all(|v| v.normal == [0.0, 0.0, 0.0]));
+    }
+
+    #[test]
+    fn tangents_follow_tex_coords() {
+        let (vertices, indices) = quad();
+        let (mut vertices, indices) = Vertex::compute_normals(vertices, indices, Normals::Smooth);
+        Vertex::compute_tangents(&mut vertices, &indices);
+        // u goes along +x, v goes along -y
+        for v in vertices.iter() {
+            assert!(Vec3::from(v.normal).abs_diff_eq(Vec3::Z, 1e-6));
+            assert_eq!(v.tangent, [1.0, 0.0, 0.0, -1.0]);
+        }
+
+        // mirrored texture flips the handedness
+        let (mut vertices, indices) = quad();
+        for v in vertices.iter_mut() {
+            v.tex_coord[1] = 1.0 - v.tex_coord[1];
+        }
+        let (mut vertices, indices) = Vertex::compute_normals(vertices, indices, Normals::Smooth);
+        Vertex::compute_tangents(&mut vertices, &indices);
+        assert!(vertices.iter().all(|v| v.tangent == [1.0, 0.0, 0.0, 1.0]));
+    }
+
+    #[test]
+    fn tangents_without_tex_coords() {
+        let (mut vertices, indices) = quad();
+        for v in vertices.iter_mut() {
+            v.tex_coord = [0.0, 0.0];
+        }
+        let (mut vertices, indices) = Vertex::compute_normals(vertices, indices, Normals::Smooth);
+        Vertex::compute_tangents(&mut vertices, &indices);
+        for v in vertices.iter() {
+            let t = Vec3::new(v.tangent[0], v.tangent[1], v.tangent[2]);
+            assert!((t.length() - 1.0).abs() < 1e-6);
+            assert!(t.dot(Vec3::from(v.normal)).abs() < 1e-6);
         }
     }
 }
@@ -0,0 +1,53 @@
+// Lights and blinn phong lighting shared by fragment shaders,
+// `#include "lights.glsl"` after the other uniforms
+#ifndef LIGHTS_GLSL
+#define LIGHTS_GLSL
+
+// light data, must match MAX_LIGHTS in render/src/light.rs
+#define MAX_LIGHTS 16
+#define POINT_LIGHT 0
+#define DIRECTIONAL_LIGHT 1
+#define SPOT_LIGHT 2
+struct Light {
+  vec3 position;
+  uint kind;
+  vec3 direction;
+  float intensity;
+  vec3 color;
+  // cosine of spot light angles
+  float cutoff;
+  float outer_cutoff;
+};
+layout(set=3, binding=0) uniform Lights {
+  uint light_count;
+  Light lights[MAX_LIGHTS];
+};
+
+// diffuse and specular light of every light at a point
+vec3 blinn_phong(vec3 position, vec3 normal, vec3 view_dir,
+                 vec3 diffuse_color, vec3 specular_color, float shininess) {
+  vec3 lighting = vec3(0.0);
+  for (uint i = 0u; i < min(light_count, uint(MAX_LIGHTS)); i++) {
+    Light light = lights[i];
+    vec3 light_dir;
+    float strength = light.intensity;
+    if (light.kind == DIRECTIONAL_LIGHT) {
+      light_dir = normalize(-light.direction);
+    } else {
+      light_dir = normalize(light.position - position);
+    }
+    if (light.kind == SPOT_LIGHT) {
+      // fade out between the inner and outer cone
+      float theta = dot(light_dir, normalize(-light.direction));
+      float epsilon = max(light.cutoff - light.outer_cutoff, 0.0001);
+      strength *= clamp((theta - light.outer_cutoff) / epsilon, 0.0, 1.0);
+    }
+    vec3 half_dir = normalize(light_dir + view_dir);
+    vec3 diffuse = diffuse_color * max(dot(light_dir, normal), 0.0);
+    vec3 specular = specular_color * pow(max(dot(normal, half_dir), 0.0), shininess);
+    lighting += (diffuse + specular) * light.color * strength;
+  }
+  return lighting;
+}
+
+#endif
@@ -19,12 +19,7 @@ layout(set=2, binding=0) uniform Material {
   vec3 u_specular;
   float u_shininess;
 };
-// light data
-layout(set=3, binding=0) uniform PointLight {
-  vec3 pl_position;
-  vec3 pl_color;
-  float pl_intensity;
-};
+#include "lights.glsl"
 
 layout(location=0) in vec3 v_position;
 layout(location=1) in vec3 v_normal;
@@ -39,13 +34,9 @@ void main() {
 
   vec3 normal = normalize(v_normal);
   vec4 obj_color = vec4(normal, 1.0) * 1.5;
-  vec3 light_dir = normalize(pl_position - v_position);
-  vec3 half_dir = normalize(light_dir + view_dir);
-  // simple blinn phong
-  vec3 diffuse = u_diffuse * max(dot(light_dir, normal), 0.0);
-  vec3 specular = u_specular * pow(max(dot(normal, half_dir), 0.0), u_shininess);
   vec3 ambient = u_ambient * 0.5 + al_color * al_intensity * 0.5;
+  vec3 lighting = blinn_phong(v_position, normal, view_dir, u_diffuse, u_specular, u_shininess);
 
-  vec3 result = (ambient * 0.2 + (diffuse + specular) * 0.8) * pl_color * pl_intensity * obj_color.xyz;
+  vec3 result = (ambient * 0.2 + lighting * 0.8) * obj_color.xyz;
   f_color = vec4(result, obj_color.a);
 }
@@ -6,6 +6,13 @@ use anyhow::Result;
 fn main() -> Result<()> {
     let cmd = core::cmd::Builder::get_args();
     let args = cmd.get_matches();
+    if let Some(args) = args.subcommand_matches("render") {
+        let mut headless = app::Headless::new(args)?;
+        return headless.run();
+    }
+    if let Some(args) = args.subcommand_matches("pack") {
+        return app::pack(args);
+    }
     let mut app = app::Application::new(&args)?;
     app.run()
 }